@@ -0,0 +1,56 @@
+// Run with `cargo bench`. The crate has no library target, so the lexer and
+// parser are pulled in by path.
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/ast/mod.rs"]
+mod ast;
+#[path = "../src/dialect.rs"]
+mod dialect;
+#[path = "../src/tokens/mod.rs"]
+mod tokens;
+
+use std::time::Instant;
+
+const LINES: u32 = 10_000;
+const RUNS: u32 = 20;
+
+fn program() -> String {
+    let mut source = String::new();
+    for line in 1..=LINES {
+        source.push_str(&line.to_string());
+        source.push_str(match line & 3 {
+            0 => " FOR I = 1 TO 10 STEP 2: PRINT \"HELLO\"; I: NEXT I\n",
+            1 => " IF CN >= 100 THEN GOSUB 500 ELSE LET TL = TL + 1\n",
+            2 => " INPUT \"NAME\"; NAME$: DATA 1, 2, 3: READ A, B\n",
+            _ => " REM a comment that runs to the end of the line\n",
+        });
+    }
+
+    source
+}
+
+fn main() {
+    let source = program();
+    let tokens = tokens::Lexer::new(&source).count();
+
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        assert_eq!(tokens::Lexer::new(&source).count(), tokens);
+    }
+    let elapsed = start.elapsed().as_micros() / u128::from(RUNS);
+
+    println!(
+        "lexed {} lines, {} tokens in {}us per run",
+        LINES, tokens, elapsed
+    );
+
+    let parse_start = Instant::now();
+    for _ in 0..RUNS {
+        let (program, errors) = ast::Parser::new(tokens::Lexer::new(&source)).parse();
+        assert!(errors.is_empty(), "unexpected errors: {}", errors.len());
+        assert_eq!(program.lines.len(), LINES as usize);
+    }
+    let parse_elapsed = parse_start.elapsed().as_micros() / u128::from(RUNS);
+
+    println!("parsed {} lines in {}us per run", LINES, parse_elapsed);
+}
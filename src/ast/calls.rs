@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{Program, Statement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Call {
+    pub target: u32,
+    // Under an IF or after one that may leave the subroutine, so it may not
+    // happen every time the subroutine runs
+    pub conditional: bool,
+}
+
+enum Flow {
+    Next,
+    Stop,
+    Jump(u32),
+}
+
+/// The GOSUBs made by each subroutine. A subroutine is every line reachable
+/// from a GOSUB target by falling through or by an unconditional GOTO, up to
+/// the first unconditional RETURN or END. Once an IF may RETURN, END or GOTO
+/// elsewhere, the calls after it are conditional.
+pub struct CallGraph {
+    calls: BTreeMap<u32, Vec<Call>>,
+}
+
+impl CallGraph {
+    pub fn new(program: &Program) -> Self {
+        let mut targets = BTreeSet::new();
+        for statement in program.values() {
+            collect_targets(statement, &mut targets);
+        }
+
+        let calls = targets
+            .into_iter()
+            .map(|start| (start, subroutine_calls(program, start)))
+            .collect();
+
+        CallGraph { calls }
+    }
+
+    pub fn calls(&self, subroutine: u32) -> &[Call] {
+        self.calls.get(&subroutine).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn subroutines(&self) -> impl Iterator<Item = u32> + '_ {
+        self.calls.keys().copied()
+    }
+
+    /// Whether `from` can reach `to`, optionally only through calls that
+    /// always happen.
+    pub fn reaches(&self, from: u32, to: u32, unconditional_only: bool) -> bool {
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![from];
+
+        while let Some(subroutine) = stack.pop() {
+            for call in self.calls(subroutine) {
+                if unconditional_only && call.conditional {
+                    continue;
+                }
+                if call.target == to {
+                    return true;
+                }
+                if visited.insert(call.target) {
+                    stack.push(call.target);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+fn collect_targets(statement: &Statement, targets: &mut BTreeSet<u32>) {
+    match statement {
+        Statement::GoSub { line_number } => {
+            targets.insert(*line_number);
+        }
+        Statement::OnGosub { targets: lines, .. } => {
+            targets.extend(lines);
+        }
+        Statement::If { then, else_, .. } => {
+            collect_targets(then, targets);
+            if let Some(else_) = else_ {
+                collect_targets(else_, targets);
+            }
+        }
+        Statement::Seq { statements } => {
+            for inner in statements {
+                collect_targets(inner, targets);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn subroutine_calls(program: &Program, start: u32) -> Vec<Call> {
+    let mut calls = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut line = start;
+    let mut may_exit = false;
+
+    'lines: loop {
+        for (&line_number, statement) in program.lines.range(line..) {
+            if !visited.insert(line_number) {
+                break 'lines;
+            }
+
+            match walk(statement, false, &mut may_exit, &mut calls) {
+                Flow::Next => {}
+                Flow::Stop => break 'lines,
+                Flow::Jump(target) => {
+                    line = target;
+                    continue 'lines;
+                }
+            }
+        }
+
+        break;
+    }
+
+    calls
+}
+
+fn walk(
+    statement: &Statement,
+    conditional: bool,
+    may_exit: &mut bool,
+    calls: &mut Vec<Call>,
+) -> Flow {
+    match statement {
+        Statement::GoSub { line_number } => {
+            calls.push(Call {
+                target: *line_number,
+                conditional: conditional || *may_exit,
+            });
+            Flow::Next
+        }
+        // Only one of the targets is called, if any
+        Statement::OnGosub { targets, .. } => {
+            calls.extend(targets.iter().map(|&target| Call {
+                target,
+                conditional: true,
+            }));
+            Flow::Next
+        }
+        Statement::Return | Statement::End if !conditional => Flow::Stop,
+        // CONT resumes after STOP, so whatever follows it still runs
+        Statement::Stop => Flow::Next,
+        Statement::Goto { line_number } if !conditional => Flow::Jump(*line_number),
+        // A base case, the rest of the subroutine may not run
+        Statement::Return | Statement::End | Statement::Goto { .. } | Statement::OnGoto { .. } => {
+            *may_exit = true;
+            Flow::Next
+        }
+        Statement::If { then, else_, .. } => {
+            walk(then, true, may_exit, calls);
+            if let Some(else_) = else_ {
+                walk(else_, true, may_exit, calls);
+            }
+            Flow::Next
+        }
+        Statement::Seq { statements } => {
+            for inner in statements {
+                match walk(inner, conditional, may_exit, calls) {
+                    Flow::Next => {}
+                    flow => return flow,
+                }
+            }
+            Flow::Next
+        }
+        _ => Flow::Next,
+    }
+}
@@ -0,0 +1,54 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Hint,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+            Severity::Hint => write!(f, "Hint"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SemKind {
+    ArgumentCount,
+    ArrayBeforeDim,
+    DivisionByZero,
+    InvalidAddress,
+    InvalidDim,
+    Redeclaration,
+    InvalidRestore,
+    LoopMismatch,
+    NoEffect,
+    Recursion,
+    OutOfMemory,
+    SuspiciousCondition,
+    SuspiciousJump,
+    TypeMismatch,
+    Unassigned,
+    UndefinedLine,
+    Unreachable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: SemKind,
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{} at line {}: {}", self.severity, line, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::tokens::LexErrorKind;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorKind {
     // Parse errors
@@ -10,8 +12,13 @@ pub enum ErrorKind {
     ExpectedRightParen,
     ExpectedStatement,
     ExpectedUnsigned,
+    ExpressionTooDeep,
     MismatchedParentheses,
+    NotInDialect,
+    NumberOutOfRange,
     UnexpectedToken,
+    // The lexer could not read the token the parser stopped at
+    Lex(LexErrorKind),
 }
 
 #[derive(Debug)]
@@ -22,7 +29,7 @@ pub struct Error {
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Error at line {}: ", self.line)?;
+        write!(f, "line {}: ", self.line)?;
         match self.kind {
             ErrorKind::ExpectedDataItem => write!(f, "Expected data item"),
             ErrorKind::ExpectedEndOfLine => write!(f, "Expected end of line"),
@@ -31,8 +38,14 @@ impl std::fmt::Display for Error {
             ErrorKind::ExpectedLineNumber => write!(f, "Expected line number"),
             ErrorKind::ExpectedStatement => write!(f, "Expected statement"),
             ErrorKind::ExpectedUnsigned => write!(f, "Expected unsigned number"),
+            ErrorKind::ExpressionTooDeep => write!(f, "Expression nested too deeply"),
             ErrorKind::MismatchedParentheses => write!(f, "Mismatched parentheses"),
+            ErrorKind::NotInDialect => {
+                write!(f, "Not supported by the PC-1500, use --dialect generic")
+            }
+            ErrorKind::NumberOutOfRange => write!(f, "Number out of range"),
             ErrorKind::UnexpectedToken => write!(f, "Unexpected token"),
+            ErrorKind::Lex(kind) => write!(f, "{}", kind),
             ErrorKind::ExpectedLeftParen => write!(f, "Expected '('"),
             ErrorKind::ExpectedRightParen => write!(f, "Expected ')'"),
         }
@@ -1,13 +1,24 @@
+mod calls;
+mod diagnostic;
 mod error;
 mod node;
 mod parser;
 mod printer;
 mod semantics;
+mod symbols;
+mod transform;
+mod uses;
 mod visitor;
 
+pub use diagnostic::Severity;
 pub use error::Error;
-pub use node::{BinaryOperator, Expression, Program, Statement, UnaryOperator};
-pub use parser::Parser;
+pub use node::{
+    format_float, BinaryOperator, DataItem, Expression, LValue, OutputDevice, Program, Separator,
+    Statement, UnaryOperator,
+};
+pub use parser::{ExpressionParser, Parser};
 pub use printer::Printer;
 pub use semantics::SemanticChecker;
+pub use symbols::{SymbolKind, SymbolTable};
+pub use transform::merge_print_literals;
 pub use visitor::{ExpressionVisitor, ProgramVisitor, StatementVisitor};
@@ -7,6 +7,10 @@ pub enum BinaryOperator {
     Sub,
     Mul,
     Div,
+    Mod,
+    Pow,
+    // String
+    Concat,
     // Logical
     And,
     Or,
@@ -27,6 +31,10 @@ impl std::fmt::Display for BinaryOperator {
             BinaryOperator::Sub => write!(f, "-"),
             BinaryOperator::Mul => write!(f, "*"),
             BinaryOperator::Div => write!(f, "/"),
+            BinaryOperator::Mod => write!(f, "MOD"),
+            BinaryOperator::Pow => write!(f, "^"),
+            // String
+            BinaryOperator::Concat => write!(f, "&"),
             // Logical
             BinaryOperator::And => write!(f, "AND"),
             BinaryOperator::Or => write!(f, "OR"),
@@ -58,12 +66,13 @@ impl std::fmt::Display for UnaryOperator {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum LValue {
     Variable(String),
     ArrayElement {
         variable: String,
-        index: Box<Expression>,
+        // One subscript per dimension
+        indices: Vec<Expression>,
     },
 }
 
@@ -71,14 +80,18 @@ impl std::fmt::Display for LValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LValue::Variable(variable) => write!(f, "{}", variable),
-            LValue::ArrayElement { variable, index } => write!(f, "{}({})", variable, index),
+            LValue::ArrayElement { variable, indices } => {
+                let indices: Vec<String> = indices.iter().map(Expression::to_string).collect();
+                write!(f, "{}({})", variable, indices.join(","))
+            }
         }
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Expression {
     Number(i32),
+    Float(f64),
     String(String),
     LValue(LValue),
     Unary {
@@ -90,6 +103,53 @@ pub enum Expression {
         op: BinaryOperator,
         right: Box<Expression>,
     },
+    // Address of a variable, the argument is checked to be one in semantics
+    Varptr(Box<Expression>),
+    // One of BUILTIN_FUNCTIONS, arguments are checked in semantics
+    Call {
+        name: String,
+        args: Vec<Expression>,
+    },
+}
+
+/// Names the parser reads as a function call rather than a variable.
+pub const BUILTIN_FUNCTIONS: [&str; 8] = [
+    "ASC", "CHR$", "LEFT$", "LEN", "MID$", "RIGHT$", "STR$", "VAL",
+];
+
+impl Expression {
+    /// Names of every variable the expression reads, in source order. Arrays
+    /// yield their base name followed by the variables in the subscript.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        let mut variables = Vec::new();
+        self.collect_variables(&mut variables);
+        variables.into_iter()
+    }
+
+    fn collect_variables<'a>(&'a self, variables: &mut Vec<&'a str>) {
+        match self {
+            Expression::Number(_) | Expression::Float(_) | Expression::String(_) => {}
+            Expression::LValue(LValue::Variable(variable)) => variables.push(variable),
+            Expression::LValue(LValue::ArrayElement { variable, indices }) => {
+                variables.push(variable);
+                for index in indices {
+                    index.collect_variables(variables);
+                }
+            }
+            Expression::Unary { operand, .. } | Expression::Varptr(operand) => {
+                operand.collect_variables(variables)
+            }
+            Expression::Call { args, .. } => {
+                for arg in args {
+                    arg.collect_variables(variables);
+                }
+            }
+            Expression::Binary { left, right, .. } => {
+                left.collect_variables(variables);
+                right.collect_variables(variables);
+            }
+        }
+    }
 }
 
 impl std::fmt::Display for Expression {
@@ -97,20 +157,68 @@ impl std::fmt::Display for Expression {
         match self {
             Expression::String(content) => write!(f, "\"{}\"", content),
             Expression::Number(value) => write!(f, "{}", value),
+            Expression::Float(value) => write!(f, "{}", format_float(*value)),
             Expression::LValue(variable) => write!(f, "{}", variable),
+            Expression::Unary {
+                op: UnaryOperator::Not,
+                operand,
+            } => write!(f, "NOT {}", operand),
             Expression::Unary { op, operand } => write!(f, "{}{}", op, operand),
             Expression::Binary { left, op, right } => write!(f, "{} {} {}", left, op, right),
+            Expression::Varptr(operand) => write!(f, "VARPTR({})", operand),
+            Expression::Call { name, args } => {
+                let args: Vec<String> = args.iter().map(Expression::to_string).collect();
+                write!(f, "{}({})", name, args.join(","))
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Writes a float so it reads back as a float, 3.14 stays 3.14 and 1500 is
+/// written 1.5E3 rather than as the integer 1500.
+pub fn format_float(value: f64) -> String {
+    let plain = value.to_string();
+    if plain.contains('.') {
+        plain
+    } else {
+        format!("{:E}", value)
+    }
+}
+
+// Numbers keep their source spelling so they can be written back unchanged
+#[derive(Debug, Clone, PartialEq)]
 pub enum DataItem {
-    Number(i32),
+    Number { value: i32, raw: String },
+    Real { value: f64, raw: String },
     String(String),
 }
 
-#[derive(Debug)]
+impl std::fmt::Display for DataItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataItem::Number { raw, .. } | DataItem::Real { raw, .. } => write!(f, "{}", raw),
+            DataItem::String(content) => write!(f, "\"{}\"", content),
+        }
+    }
+}
+
+// Where PRINT sends its output, LPRINT goes to the CE-150 printer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputDevice {
+    Display,
+    Printer,
+}
+
+// What follows a PRINT item: `;` prints the next item right after it, `,`
+// moves to the next half of the display first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Separator {
+    Semicolon,
+    Comma,
+    None,
+}
+
+#[derive(Debug, PartialEq)]
 pub enum Statement {
     Let {
         variable: LValue,
@@ -118,16 +226,23 @@ pub enum Statement {
     },
     Dim {
         variable: String,
-        size: u32,
+        sizes: Vec<u32>,
         length: Option<u32>, // Only for strings
     },
     Print {
-        content: Vec<Expression>,
+        content: Vec<(Expression, Separator)>,
+        // PRINT ; A keeps printing on the current display line
+        continue_line: bool,
+        // PRINT A; leaves the cursor after A
+        suppress_newline: bool,
+        device: OutputDevice,
     },
     Pause {
         content: Vec<Expression>,
     },
     Input {
+        // INPUT #n reads from a device, None is the keyboard
+        device: Option<Expression>,
         prompt: Option<Expression>,
         variable: LValue,
     },
@@ -157,15 +272,26 @@ pub enum Statement {
         step: Option<Expression>,
     },
     Next {
-        variable: String,
+        variable: Option<String>,
     },
     Goto {
         line_number: u32,
     },
     End,
+    // Halts like END, but CONT resumes after it
+    Stop,
     GoSub {
         line_number: u32,
     },
+    // Jumps to the selector'th target, falls through when it is out of range
+    OnGoto {
+        selector: Expression,
+        targets: Vec<u32>,
+    },
+    OnGosub {
+        selector: Expression,
+        targets: Vec<u32>,
+    },
     Return,
     If {
         condition: Expression,
@@ -178,6 +304,14 @@ pub enum Statement {
     Rem {
         content: String,
     },
+    Clear {
+        // Optionally also reserves string memory
+        size: Option<Expression>,
+    },
+    // Loads and runs another program, generic dialect only
+    Chain {
+        name: Expression,
+    },
 }
 
 #[derive(Debug)]
@@ -200,6 +334,15 @@ impl Program {
         self.lines.get(&line_number)
     }
 
+    /// The line itself if it exists, otherwise the first line after it. This
+    /// is where a jump to a missing line ends up on the PC-1500.
+    pub fn lookup_line_or_next(&self, line_number: u32) -> Option<(u32, &Statement)> {
+        self.lines
+            .range(line_number..)
+            .next()
+            .map(|(&line_number, statement)| (line_number, statement))
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&u32, &Statement)> {
         self.lines.iter()
     }
@@ -207,4 +350,225 @@ impl Program {
     pub fn values(&self) -> impl Iterator<Item = &Statement> {
         self.lines.values()
     }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Statement> {
+        self.lines.values_mut()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Numbers the lines `start`, `start + step`, ... in their current order
+    /// and rewrites every GOTO, GOSUB, RESTORE and ON target to match. The
+    /// program is left unchanged if a target is not an existing line.
+    pub fn renumber(&mut self, start: u32, step: u32) -> Result<(), RenumberError> {
+        let mut numbers = BTreeMap::new();
+        let mut next = Some(start);
+        for &line_number in self.lines.keys() {
+            let new = next.ok_or(RenumberError::TooManyLines)?;
+            numbers.insert(line_number, new);
+            next = new.checked_add(step);
+        }
+
+        for (&line_number, statement) in &mut self.lines {
+            let mut missing = None;
+            line_references(statement, &mut |target| {
+                if !numbers.contains_key(target) {
+                    missing.get_or_insert(*target);
+                }
+            });
+
+            if let Some(target) = missing {
+                return Err(RenumberError::MissingTarget {
+                    line: line_number,
+                    target,
+                });
+            }
+        }
+
+        self.lines = std::mem::take(&mut self.lines)
+            .into_iter()
+            .map(|(line_number, mut statement)| {
+                line_references(&mut statement, &mut |target| *target = numbers[target]);
+                (numbers[&line_number], statement)
+            })
+            .collect();
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RenumberError {
+    MissingTarget { line: u32, target: u32 },
+    TooManyLines,
+}
+
+impl std::fmt::Display for RenumberError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenumberError::MissingTarget { line, target } => write!(
+                f,
+                "line {} refers to line {}, which does not exist",
+                line, target
+            ),
+            RenumberError::TooManyLines => write!(f, "line numbers do not fit"),
+        }
+    }
+}
+
+// Every line number a statement refers to
+fn line_references(statement: &mut Statement, f: &mut impl FnMut(&mut u32)) {
+    match statement {
+        Statement::Goto { line_number }
+        | Statement::GoSub { line_number }
+        | Statement::Restore {
+            line_number: Some(line_number),
+        } => f(line_number),
+        Statement::OnGoto { targets, .. } | Statement::OnGosub { targets, .. } => {
+            targets.iter_mut().for_each(f);
+        }
+        Statement::If { then, else_, .. } => {
+            line_references(then, f);
+            if let Some(else_) = else_ {
+                line_references(else_, f);
+            }
+        }
+        Statement::Seq { statements } => {
+            for inner in statements {
+                line_references(inner, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expression_variables() {
+        // A + B(C)
+        let expression = Expression::Binary {
+            left: Box::new(Expression::LValue(LValue::Variable("A".to_owned()))),
+            op: BinaryOperator::Add,
+            right: Box::new(Expression::LValue(LValue::ArrayElement {
+                variable: "B".to_owned(),
+                indices: vec![Expression::LValue(LValue::Variable("C".to_owned()))],
+            })),
+        };
+
+        let variables: Vec<&str> = expression.variables().collect();
+
+        assert_eq!(variables, vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn float_display_reads_back_as_float() {
+        assert_eq!(Expression::Float(3.25).to_string(), "3.25");
+        assert_eq!(Expression::Float(1500.0).to_string(), "1.5E3");
+    }
+
+    #[test]
+    fn renumber_rewrites_targets() {
+        let mut program = Program::new();
+        program.add_line(5, Statement::Goto { line_number: 30 });
+        program.add_line(
+            7,
+            Statement::If {
+                condition: Expression::Number(1),
+                then: Box::new(Statement::Seq {
+                    statements: vec![
+                        Statement::GoSub { line_number: 5 },
+                        Statement::Restore {
+                            line_number: Some(7),
+                        },
+                    ],
+                }),
+                else_: None,
+            },
+        );
+        program.add_line(
+            30,
+            Statement::OnGoto {
+                selector: Expression::Number(1),
+                targets: vec![30, 7],
+            },
+        );
+
+        assert_eq!(program.renumber(100, 10), Ok(()));
+
+        let lines: Vec<u32> = program.lines.keys().copied().collect();
+        assert_eq!(lines, vec![100, 110, 120]);
+        assert_eq!(
+            program.lookup_line(100),
+            Some(&Statement::Goto { line_number: 120 })
+        );
+        assert_eq!(
+            program.lookup_line(110),
+            Some(&Statement::If {
+                condition: Expression::Number(1),
+                then: Box::new(Statement::Seq {
+                    statements: vec![
+                        Statement::GoSub { line_number: 100 },
+                        Statement::Restore {
+                            line_number: Some(110),
+                        },
+                    ],
+                }),
+                else_: None,
+            })
+        );
+        assert_eq!(
+            program.lookup_line(120),
+            Some(&Statement::OnGoto {
+                selector: Expression::Number(1),
+                targets: vec![120, 110],
+            })
+        );
+    }
+
+    #[test]
+    fn renumber_missing_target_errors() {
+        let mut program = Program::new();
+        program.add_line(10, Statement::End);
+        program.add_line(20, Statement::Goto { line_number: 15 });
+
+        assert_eq!(
+            program.renumber(100, 10),
+            Err(RenumberError::MissingTarget {
+                line: 20,
+                target: 15
+            })
+        );
+        // Nothing was renumbered
+        assert_eq!(
+            program.lookup_line(20),
+            Some(&Statement::Goto { line_number: 15 })
+        );
+
+        let mut long = Program::new();
+        long.add_line(10, Statement::End);
+        long.add_line(20, Statement::End);
+        assert_eq!(long.renumber(u32::MAX, 1), Err(RenumberError::TooManyLines));
+    }
+
+    #[test]
+    fn lookup_line_or_next() {
+        let mut program = Program::new();
+        program.add_line(10, Statement::End);
+        program.add_line(20, Statement::Return);
+
+        let target = |line_number| {
+            program
+                .lookup_line_or_next(line_number)
+                .map(|(target, _)| target)
+        };
+
+        assert_eq!(target(15), Some(20));
+        assert_eq!(target(10), Some(10));
+        assert_eq!(target(21), None);
+    }
 }
@@ -1,70 +1,158 @@
 use crate::ast::{
-    error::ErrorKind, node::LValue, BinaryOperator, Error, Expression, UnaryOperator,
+    error::ErrorKind,
+    node::{LValue, BUILTIN_FUNCTIONS},
+    BinaryOperator, Error, Expression, UnaryOperator,
 };
 use crate::tokens::{Lexer, Token};
-use std::{iter::Peekable, mem};
+use std::{cell::Cell, iter::Peekable, mem, rc::Rc};
+
+// Each level of nesting costs several stack frames, this keeps pathological
+// input like ((((...)))) from overflowing the stack
+const DEFAULT_MAX_DEPTH: usize = 128;
+
+// The largest number the PC-1500 can hold
+const MAX_NUMBER: f64 = 9.9999999999E99;
+
+pub(super) fn is_in_range(value: f64) -> bool {
+    value.is_finite() && value.abs() <= MAX_NUMBER
+}
 
 pub struct ExpressionParser<'a> {
     lexer: Peekable<Lexer<'a>>,
+    line: Rc<Cell<usize>>,
+    depth: usize,
+    max_depth: usize,
 }
 
 impl<'a> ExpressionParser<'a> {
-    pub fn new(lexer: Peekable<Lexer<'a>>) -> Self {
-        Self { lexer }
+    pub fn new(lexer: Lexer<'a>) -> Self {
+        Self {
+            line: lexer.line_counter(),
+            lexer: lexer.peekable(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    // The statement parser reads its tokens from the same lexer
+    pub(super) fn lexer(&mut self) -> &mut Peekable<Lexer<'a>> {
+        &mut self.lexer
+    }
+
+    // Reported on the line of the last token read, which is the one that
+    // did not fit when a token was only peeked at. If the lexer could not
+    // read that token, its error says more than what the parser expected.
+    pub(super) fn error(&mut self, kind: ErrorKind) -> Error {
+        let kind = match self.lexer.peek() {
+            Some(Token::Error(error)) => ErrorKind::Lex(error.kind),
+            _ => kind,
+        };
+
+        Error {
+            kind,
+            line: self.line.get(),
+        }
     }
 
     pub fn parse(&mut self) -> Result<Option<Expression>, Error> {
         // println!("expression");
-        self.comparison()
+        self.nested(Self::logical_or)
     }
 
-    fn lvalue(&mut self) -> Result<LValue, Error> {
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        if self.depth >= self.max_depth {
+            return Err(self.error(ErrorKind::ExpressionTooDeep));
+        }
+
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
+    }
+
+    pub(super) fn lvalue(&mut self) -> Result<LValue, Error> {
         // println!("lvalue");
-        match self.lexer.peek_mut() {
-            Some(Token::Identifier(v)) => {
-                let variable = mem::take(v);
-                let next = self.lexer.next();
-
-                // println!("identifier {}", v);
-
-                if next == Some(Token::LeftParen) {
-                    self.lexer.next();
-                    let index = self.parse()?;
-                    if self.lexer.peek() == Some(&Token::RightParen) {
-                        let res = Ok(LValue::ArrayElement {
-                            variable,
-                            index: Box::new(index.unwrap()),
-                        });
-
-                        self.lexer.next();
-
-                        res
-                    } else {
-                        Err(Error {
-                            kind: ErrorKind::MismatchedParentheses,
-                            line: 0, // TODO
-                        })
-                    }
-                } else {
-                    Ok(LValue::Variable(variable))
-                }
-            }
+        let variable = match self.lexer.peek_mut() {
+            Some(Token::Identifier(v)) => mem::take(v),
             _ => {
                 // println!("expected identifier");
-                Err(Error {
-                    kind: ErrorKind::ExpectedIdentifier,
-                    line: 0, // TODO
-                })
+                return Err(self.error(ErrorKind::ExpectedIdentifier));
             }
+        };
+
+        self.lexer.next();
+
+        if self.lexer.next_if_eq(&Token::LeftParen).is_none() {
+            return Ok(LValue::Variable(variable));
+        }
+
+        let indices = self.arguments()?;
+
+        if self.lexer.next_if_eq(&Token::RightParen).is_none() {
+            return Err(self.error(ErrorKind::MismatchedParentheses));
+        }
+
+        Ok(LValue::ArrayElement { variable, indices })
+    }
+
+    // Comma separated, used for subscripts and function arguments
+    fn arguments(&mut self) -> Result<Vec<Expression>, Error> {
+        let mut arguments = vec![self.required()?];
+        while self.lexer.next_if_eq(&Token::Comma).is_some() {
+            arguments.push(self.required()?);
+        }
+
+        Ok(arguments)
+    }
+
+    fn required(&mut self) -> Result<Expression, Error> {
+        match self.parse()? {
+            Some(expression) => Ok(expression),
+            None => Err(self.error(ErrorKind::ExpectedExpression)),
+        }
+    }
+
+    fn function(&mut self, name: String) -> Result<Expression, Error> {
+        self.lexer.next();
+        if self.lexer.next_if_eq(&Token::LeftParen).is_none() {
+            return Err(self.error(ErrorKind::ExpectedLeftParen));
+        }
+
+        let args = self.arguments()?;
+
+        if self.lexer.next_if_eq(&Token::RightParen).is_none() {
+            return Err(self.error(ErrorKind::ExpectedRightParen));
         }
+
+        Ok(Expression::Call { name, args })
     }
 
     fn term(&mut self) -> Result<Option<Expression>, Error> {
         match self.lexer.peek_mut() {
-            Some(Token::Number(n)) => {
-                let res = Ok(Some(Expression::Number(*n)));
+            // Numbers are floating point, an integer too big for i32 is still one
+            Some(&mut Token::Number(n)) => {
                 self.lexer.next();
-                res
+                Ok(Some(match i32::try_from(n) {
+                    Ok(n) => Expression::Number(n),
+                    Err(_) => Expression::Float(n as f64),
+                }))
+            }
+            Some(&mut Token::Float(n)) => {
+                self.lexer.next();
+                if is_in_range(n) {
+                    Ok(Some(Expression::Float(n)))
+                } else {
+                    Err(self.error(ErrorKind::NumberOutOfRange))
+                }
+            }
+            Some(Token::Identifier(name)) if BUILTIN_FUNCTIONS.contains(&name.as_str()) => {
+                let name = mem::take(name);
+                self.function(name).map(Some)
             }
             Some(Token::Identifier(_)) => self.lvalue().map(|v| Some(Expression::LValue(v))),
             Some(Token::String(s)) => {
@@ -74,39 +162,52 @@ impl<'a> ExpressionParser<'a> {
             }
             Some(Token::LeftParen) => {
                 self.lexer.next();
-                let res = self.parse()?;
-                // if self.lexer.next() == Some(Token::RightParen) {
-                //     Ok(res)
-                // } else {
-                //     Err(Error {
-                //         kind: ErrorKind::MismatchedParentheses,
-                //         line: 0, // TODO
-                //     })
-                // }
-                Ok(res)
+                let inner = self.required()?;
+
+                if self.lexer.next_if_eq(&Token::RightParen).is_none() {
+                    return Err(self.error(ErrorKind::MismatchedParentheses));
+                }
+
+                Ok(Some(inner))
+            }
+            Some(Token::Varptr) => {
+                self.lexer.next();
+                if self.lexer.next() != Some(Token::LeftParen) {
+                    return Err(self.error(ErrorKind::ExpectedLeftParen));
+                }
+
+                let operand = if let Some(operand) = self.parse()? {
+                    operand
+                } else {
+                    return Err(self.error(ErrorKind::ExpectedExpression));
+                };
+
+                if self.lexer.next() != Some(Token::RightParen) {
+                    return Err(self.error(ErrorKind::ExpectedRightParen));
+                }
+
+                Ok(Some(Expression::Varptr(Box::new(operand))))
             }
             _ => Ok(None),
         }
     }
 
-    // unary + and -
+    // unary +, - and NOT
     fn factor(&mut self) -> Result<Option<Expression>, Error> {
         // println!("factor");
-        if self.lexer.peek() == Some(&Token::Plus) || self.lexer.peek() == Some(&Token::Minus) {
+        if let Some(&Token::Plus) | Some(&Token::Minus) | Some(&Token::Not) = self.lexer.peek() {
             let op = match self.lexer.next() {
                 Some(Token::Plus) => UnaryOperator::Plus,
                 Some(Token::Minus) => UnaryOperator::Minus,
+                Some(Token::Not) => UnaryOperator::Not,
                 _ => unreachable!(),
             };
 
-            let operand = self.factor();
+            let operand = self.nested(Self::factor);
             let operand = if let Some(operand) = operand? {
                 operand
             } else {
-                return Err(Error {
-                    kind: ErrorKind::ExpectedExpression,
-                    line: 0, // TODO
-                });
+                return Err(self.error(ErrorKind::ExpectedExpression));
             };
 
             Ok(Some(Expression::Unary {
@@ -114,8 +215,35 @@ impl<'a> ExpressionParser<'a> {
                 operand: Box::new(operand),
             }))
         } else {
-            self.term()
+            self.power()
+        }
+    }
+
+    // ^ binds tighter than unary minus, -2^2 is -(2^2), and is right
+    // associative, 2^3^2 is 2^(3^2)
+    fn power(&mut self) -> Result<Option<Expression>, Error> {
+        let base = if let Some(base) = self.term()? {
+            base
+        } else {
+            return Ok(None);
+        };
+
+        if self.lexer.next_if_eq(&Token::Caret).is_none() {
+            return Ok(Some(base));
         }
+
+        // The exponent may have its own sign, as in 2^-1
+        let exponent = if let Some(exponent) = self.nested(Self::factor)? {
+            exponent
+        } else {
+            return Err(self.error(ErrorKind::ExpectedExpression));
+        };
+
+        Ok(Some(Expression::Binary {
+            left: Box::new(base),
+            op: BinaryOperator::Pow,
+            right: Box::new(exponent),
+        }))
     }
 
     fn mul_div(&mut self) -> Result<Option<Expression>, Error> {
@@ -125,10 +253,11 @@ impl<'a> ExpressionParser<'a> {
             return Ok(None);
         };
 
-        while let Some(&Token::Star) | Some(&Token::Slash) = self.lexer.peek() {
+        while let Some(&Token::Star) | Some(&Token::Slash) | Some(&Token::Mod) = self.lexer.peek() {
             let op = match self.lexer.next() {
                 Some(Token::Star) => BinaryOperator::Mul,
                 Some(Token::Slash) => BinaryOperator::Div,
+                Some(Token::Mod) => BinaryOperator::Mod,
                 _ => unreachable!(),
             };
 
@@ -136,10 +265,7 @@ impl<'a> ExpressionParser<'a> {
             let right = if let Some(right) = right? {
                 right
             } else {
-                return Err(Error {
-                    kind: ErrorKind::ExpectedExpression,
-                    line: 0, // TODO
-                });
+                return Err(self.error(ErrorKind::ExpectedExpression));
             };
 
             left = Expression::Binary {
@@ -159,12 +285,13 @@ impl<'a> ExpressionParser<'a> {
             return Ok(None);
         };
 
-        while let Some(&Token::Plus) | Some(&Token::Minus) = self.lexer.peek() {
-            println!("add_sub");
-
+        while let Some(&Token::Plus) | Some(&Token::Minus) | Some(&Token::Ampersand) =
+            self.lexer.peek()
+        {
             let op = match self.lexer.next() {
                 Some(Token::Plus) => BinaryOperator::Add,
                 Some(Token::Minus) => BinaryOperator::Sub,
+                Some(Token::Ampersand) => BinaryOperator::Concat,
                 _ => unreachable!(),
             };
 
@@ -172,10 +299,7 @@ impl<'a> ExpressionParser<'a> {
             let right = if let Some(right) = right? {
                 right
             } else {
-                return Err(Error {
-                    kind: ErrorKind::ExpectedExpression,
-                    line: 0, // TODO
-                });
+                return Err(self.error(ErrorKind::ExpectedExpression));
             };
 
             left = Expression::Binary {
@@ -216,10 +340,7 @@ impl<'a> ExpressionParser<'a> {
             let right = if let Some(right) = right? {
                 right
             } else {
-                return Err(Error {
-                    kind: ErrorKind::ExpectedExpression,
-                    line: 0, // TODO
-                });
+                return Err(self.error(ErrorKind::ExpectedExpression));
             };
 
             left = Expression::Binary {
@@ -231,11 +352,62 @@ impl<'a> ExpressionParser<'a> {
 
         Ok(Some(left))
     }
+
+    // AND binds tighter than OR, both looser than comparisons, so
+    // A = 1 AND B = 2 OR C = 3 is ((A = 1) AND (B = 2)) OR (C = 3)
+    fn logical_and(&mut self) -> Result<Option<Expression>, Error> {
+        let mut left = if let Some(left) = self.comparison()? {
+            left
+        } else {
+            return Ok(None);
+        };
+
+        while self.lexer.next_if_eq(&Token::And).is_some() {
+            let right = if let Some(right) = self.comparison()? {
+                right
+            } else {
+                return Err(self.error(ErrorKind::ExpectedExpression));
+            };
+
+            left = Expression::Binary {
+                left: Box::new(left),
+                op: BinaryOperator::And,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(Some(left))
+    }
+
+    fn logical_or(&mut self) -> Result<Option<Expression>, Error> {
+        let mut left = if let Some(left) = self.logical_and()? {
+            left
+        } else {
+            return Ok(None);
+        };
+
+        while self.lexer.next_if_eq(&Token::Or).is_some() {
+            let right = if let Some(right) = self.logical_and()? {
+                right
+            } else {
+                return Err(self.error(ErrorKind::ExpectedExpression));
+            };
+
+            left = Expression::Binary {
+                left: Box::new(left),
+                op: BinaryOperator::Or,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(Some(left))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::Printer;
 
     #[test]
     fn add_sub_1() {
@@ -250,7 +422,7 @@ mod tests {
         };
 
         let lexer = Lexer::new("1 + 2 - 3");
-        let mut parser = ExpressionParser::new(lexer.peekable());
+        let mut parser = ExpressionParser::new(lexer);
 
         let res = parser
             .add_sub()
@@ -274,7 +446,7 @@ mod tests {
         };
 
         let lexer = Lexer::new("1 * 2 / 3");
-        let mut parser = ExpressionParser::new(lexer.peekable());
+        let mut parser = ExpressionParser::new(lexer);
 
         let res = parser
             .mul_div()
@@ -284,12 +456,72 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn mod_binds_like_div() {
+        let expected = Expression::Binary {
+            left: Box::new(Expression::Number(1)),
+            op: BinaryOperator::Add,
+            right: Box::new(Expression::Binary {
+                left: Box::new(Expression::Number(7)),
+                op: BinaryOperator::Mod,
+                right: Box::new(Expression::Number(3)),
+            }),
+        };
+
+        let lexer = Lexer::new("1 + 7 MOD 3");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .add_sub()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, expected);
+    }
+
     #[test]
     fn lvalue_1() {
         let expected = LValue::Variable("A".to_owned());
 
         let lexer = Lexer::new("A");
-        let mut parser = ExpressionParser::new(lexer.peekable());
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser.lvalue().expect("Failed to parse lvalue");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn lvalue_array() {
+        let expected = LValue::ArrayElement {
+            variable: "A".to_owned(),
+            indices: vec![Expression::LValue(LValue::Variable("I".to_owned()))],
+        };
+
+        let lexer = Lexer::new("A(I)");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser.lvalue().expect("Failed to parse lvalue");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn lvalue_two_dimensional() {
+        let expected = LValue::ArrayElement {
+            variable: "A".to_owned(),
+            indices: vec![
+                Expression::LValue(LValue::Variable("I".to_owned())),
+                Expression::Binary {
+                    left: Box::new(Expression::LValue(LValue::Variable("J".to_owned()))),
+                    op: BinaryOperator::Add,
+                    right: Box::new(Expression::Number(1)),
+                },
+            ],
+        };
+
+        let lexer = Lexer::new("A(I,J+1)");
+        let mut parser = ExpressionParser::new(lexer);
 
         let res = parser.lvalue().expect("Failed to parse lvalue");
 
@@ -301,7 +533,7 @@ mod tests {
         let expected = Expression::Number(42);
 
         let lexer = Lexer::new("42");
-        let mut parser = ExpressionParser::new(lexer.peekable());
+        let mut parser = ExpressionParser::new(lexer);
 
         let res = parser
             .factor()
@@ -320,7 +552,7 @@ mod tests {
         };
 
         let lexer = Lexer::new("+42");
-        let mut parser = ExpressionParser::new(lexer.peekable());
+        let mut parser = ExpressionParser::new(lexer);
 
         let res = parser
             .factor()
@@ -339,7 +571,7 @@ mod tests {
         };
 
         let lexer = Lexer::new("-42");
-        let mut parser = ExpressionParser::new(lexer.peekable());
+        let mut parser = ExpressionParser::new(lexer);
 
         let res = parser
             .factor()
@@ -360,7 +592,160 @@ mod tests {
 
         let lexer = Lexer::new("(42 * 43)");
 
-        let mut parser = ExpressionParser::new(lexer.peekable());
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .term()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn nested_parentheses() {
+        let lexer = Lexer::new("((1)) * (A(2))");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .parse()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(Printer::new().build_expression(&res), "(1 * A(2))");
+    }
+
+    #[test]
+    fn unbalanced_parentheses() {
+        for input in ["(1 + 2", "((1)", "(A(2)"] {
+            let mut parser = ExpressionParser::new(Lexer::new(input));
+
+            assert_eq!(
+                parser.parse().map_err(|error| error.kind),
+                Err(ErrorKind::MismatchedParentheses),
+                "{}",
+                input
+            );
+        }
+
+        let mut parser = ExpressionParser::new(Lexer::new("()"));
+        assert_eq!(
+            parser.parse().map_err(|error| error.kind),
+            Err(ErrorKind::ExpectedExpression)
+        );
+    }
+
+    #[test]
+    fn float_literal() {
+        let lexer = Lexer::new("1.5E3");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .term()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, Expression::Float(1500.0));
+    }
+
+    #[test]
+    fn power_binds_tighter_than_mul() {
+        let expected = Expression::Binary {
+            left: Box::new(Expression::Binary {
+                left: Box::new(Expression::Number(2)),
+                op: BinaryOperator::Pow,
+                right: Box::new(Expression::Number(3)),
+            }),
+            op: BinaryOperator::Mul,
+            right: Box::new(Expression::Number(4)),
+        };
+
+        let lexer = Lexer::new("2 ^ 3 * 4");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .parse()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        let expected = Expression::Binary {
+            left: Box::new(Expression::Number(2)),
+            op: BinaryOperator::Pow,
+            right: Box::new(Expression::Binary {
+                left: Box::new(Expression::Number(3)),
+                op: BinaryOperator::Pow,
+                right: Box::new(Expression::Number(2)),
+            }),
+        };
+
+        let lexer = Lexer::new("2^3^2");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .parse()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn power_before_unary_minus() {
+        let expected = Expression::Unary {
+            op: UnaryOperator::Minus,
+            operand: Box::new(Expression::Binary {
+                left: Box::new(Expression::Number(2)),
+                op: BinaryOperator::Pow,
+                right: Box::new(Expression::Number(2)),
+            }),
+        };
+
+        let lexer = Lexer::new("-2^2");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .parse()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn not_takes_a_factor() {
+        let expected = Expression::Binary {
+            left: Box::new(Expression::Unary {
+                op: UnaryOperator::Not,
+                operand: Box::new(Expression::LValue(LValue::Variable("A".to_owned()))),
+            }),
+            op: BinaryOperator::And,
+            right: Box::new(Expression::LValue(LValue::Variable("B".to_owned()))),
+        };
+
+        let lexer = Lexer::new("NOT A AND B");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .parse()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn varptr() {
+        let expected = Expression::Varptr(Box::new(Expression::LValue(LValue::Variable(
+            "A".to_owned(),
+        ))));
+
+        let lexer = Lexer::new("VARPTR(A)");
+        let mut parser = ExpressionParser::new(lexer);
 
         let res = parser
             .term()
@@ -370,6 +755,124 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn builtin_function() {
+        let expected = Expression::Call {
+            name: "MID$".to_owned(),
+            args: vec![
+                Expression::LValue(LValue::Variable("A$".to_owned())),
+                Expression::Number(2),
+                Expression::Call {
+                    name: "LEN".to_owned(),
+                    args: vec![Expression::String("ab".to_owned())],
+                },
+            ],
+        };
+
+        let lexer = Lexer::new("MID$(A$,2,LEN(\"ab\"))");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .term()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn deep_nesting_errors() {
+        let input = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+        let lexer = Lexer::new(&input);
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser.parse();
+
+        assert!(matches!(
+            res,
+            Err(Error {
+                kind: ErrorKind::ExpressionTooDeep,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn ampersand_concat() {
+        let expected = Expression::Binary {
+            left: Box::new(Expression::LValue(LValue::Variable("A$".to_owned()))),
+            op: BinaryOperator::Concat,
+            right: Box::new(Expression::LValue(LValue::Variable("B$".to_owned()))),
+        };
+
+        let lexer = Lexer::new("A$ & B$");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .add_sub()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn custom_max_depth() {
+        let lexer = Lexer::new("((1))");
+        let mut parser = ExpressionParser::new(lexer).max_depth(2);
+
+        assert!(matches!(
+            parser.parse(),
+            Err(Error {
+                kind: ErrorKind::ExpressionTooDeep,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn large_integer_is_float() {
+        let lexer = Lexer::new("99999999999");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .term()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, Expression::Float(99_999_999_999.0));
+    }
+
+    #[test]
+    fn float_out_of_range() {
+        for input in ["1E400", "1E100"] {
+            let mut parser = ExpressionParser::new(Lexer::new(input));
+
+            assert_eq!(
+                parser.term().map_err(|error| error.kind),
+                Err(ErrorKind::NumberOutOfRange),
+                "{}",
+                input
+            );
+        }
+
+        let mut parser = ExpressionParser::new(Lexer::new("9.9999999999E99"));
+        assert!(parser.term().is_ok_and(|res| res.is_some()));
+    }
+
+    #[test]
+    fn number_at_limit() {
+        let lexer = Lexer::new("2147483647");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .term()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(res, Expression::Number(i32::MAX));
+    }
+
     #[test]
     fn comparison_eq() {
         let expected = Expression::Binary {
@@ -379,7 +882,7 @@ mod tests {
         };
 
         let lexer = Lexer::new("42 = 43");
-        let mut parser = ExpressionParser::new(lexer.peekable());
+        let mut parser = ExpressionParser::new(lexer);
 
         let res = parser
             .comparison()
@@ -388,4 +891,31 @@ mod tests {
 
         assert_eq!(res, expected);
     }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let lexer = Lexer::new("A = 1 AND B = 2 OR C = 3");
+        let mut parser = ExpressionParser::new(lexer);
+
+        let res = parser
+            .parse()
+            .expect("Failed to parse expression")
+            .expect("Expected an expression");
+
+        assert_eq!(
+            Printer::new().build_expression(&res),
+            "(((A = 1) AND (B = 2)) OR (C = 3))"
+        );
+    }
+
+    #[test]
+    fn and_needs_right_operand() {
+        let lexer = Lexer::new("A AND");
+        let mut parser = ExpressionParser::new(lexer);
+
+        assert_eq!(
+            parser.parse().map_err(|error| error.kind),
+            Err(ErrorKind::ExpectedExpression)
+        );
+    }
 }
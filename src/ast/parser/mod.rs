@@ -1,702 +1,1202 @@
 mod expression;
 
-use std::iter::Peekable;
-use std::mem;
+use std::{iter::Peekable, mem};
 
 use super::error::ErrorKind;
-use super::node::{DataItem, LValue, UnaryOperator};
+use super::node::{format_float, DataItem, LValue, OutputDevice, Separator};
 use super::{BinaryOperator, Error, Expression, Program, Statement};
+use crate::dialect::Dialect;
 use crate::tokens::{Lexer, Token};
 
+pub use expression::ExpressionParser;
+
 pub struct Parser<'a> {
-    lexer: Peekable<Lexer<'a>>,
+    expression_parser: ExpressionParser<'a>,
+    dialect: Dialect,
+}
+
+// A = B = 0 parses as A = (B = 0), a comparison. In the generic dialect it
+// assigns 0 to both instead: the value goes into the last target and is then
+// copied outwards, so it is only evaluated once.
+fn assignment(dialect: Dialect, variable: LValue, expression: Expression) -> Statement {
+    // Comparisons are left associative, B = C = 0 is (B = C) = 0, so the
+    // targets are down the left side
+    fn all_targets(expression: &Expression) -> bool {
+        match expression {
+            Expression::LValue(_) => true,
+            Expression::Binary {
+                left,
+                op: BinaryOperator::Eq,
+                right,
+            } => matches!(**right, Expression::LValue(_)) && all_targets(left),
+            _ => false,
+        }
+    }
+
+    let is_chain = matches!(
+        &expression,
+        Expression::Binary {
+            left,
+            op: BinaryOperator::Eq,
+            ..
+        } if all_targets(left)
+    );
+
+    if dialect != Dialect::Generic || !is_chain {
+        return Statement::Let {
+            variable,
+            expression,
+        };
+    }
+
+    let mut operands = Vec::new();
+    let mut rest = expression;
+    while let Expression::Binary {
+        left,
+        op: BinaryOperator::Eq,
+        right,
+    } = rest
+    {
+        operands.push(*right);
+        rest = *left;
+    }
+    operands.push(rest);
+
+    // operands is now the value followed by the targets from right to left
+    let value = operands.remove(0);
+    let mut targets = vec![variable];
+    targets.extend(operands.into_iter().rev().map(|operand| match operand {
+        Expression::LValue(target) => target,
+        _ => unreachable!("all_targets checked every target is a variable"),
+    }));
+
+    let mut statements = Vec::with_capacity(targets.len());
+    let mut source = targets.pop().unwrap();
+    statements.push(Statement::Let {
+        variable: source.clone(),
+        expression: value,
+    });
+
+    while let Some(target) = targets.pop() {
+        statements.push(Statement::Let {
+            variable: target.clone(),
+            expression: Expression::LValue(source),
+        });
+        source = target;
+    }
+
+    Statement::Seq { statements }
 }
 
 impl<'a> Parser<'a> {
     pub fn new(lexer: Lexer<'a>) -> Self {
         Self {
-            lexer: lexer.peekable(),
+            expression_parser: ExpressionParser::new(lexer),
+            dialect: Dialect::Pc1500,
         }
     }
 
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.expression_parser = self.expression_parser.max_depth(max_depth);
+        self
+    }
+
     pub fn parse(&mut self) -> (Program, Vec<Error>) {
-        // self.program()
-        todo!("parse")
-    }
-
-    // fn let_(&mut self) -> Result<Statement, Error> {
-    //     // println!("let");
-    //     let variable = match &mut self.current_token {
-    //         // Optional LET keyword
-    //         Some(Token::Let) => {
-    //             self.current_token = self.lexer.next();
-
-    //             match self.current_token {
-    //                 Some(Token::Identifier(_)) => self.lvalue()?,
-    //                 _ => {
-    //                     return Err(Error {
-    //                         kind: ErrorKind::ExpectedIdentifier,
-    //                         line: self.lexer.current_line(),
-    //                     });
-    //                 }
-    //             }
-    //         }
-    //         Some(Token::Identifier(v)) => {
-    //             self.current_token = Some(Token::Identifier(mem::take(v)));
-    //             println!("identifier");
-    //             self.lvalue()?
-    //         }
-    //         _ => {
-    //             unreachable!("We already checked for LET or identifier");
-    //         }
-    //     };
-
-    //     // println!("variable: {:?}", variable);
-    //     // println!("current_token: {:?}", self.current_token);
-
-    //     if self.current_token != Some(Token::Equal) {
-    //         // println!("not equal {:?}", self.current_token);
-    //         return Err(Error {
-    //             kind: ErrorKind::UnexpectedToken,
-    //             line: self.lexer.current_line(),
-    //         });
-    //     }
-
-    //     println!("equal");
-
-    //     self.current_token = self.lexer.next();
-    //     let expression = self.expression()?;
-    //     let expression = if let Some(expression) = expression {
-    //         println!("expression");
-    //         expression
-    //     } else {
-    //         println!("no expression");
-    //         return Err(Error {
-    //             kind: ErrorKind::ExpectedExpression,
-    //             line: self.lexer.current_line(),
-    //         });
-    //     };
-
-    //     Ok(Statement::Let {
-    //         variable,
-    //         expression,
-    //     })
-    // }
-
-    // fn pause(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let mut content = Vec::new();
-
-    //     while let Some(expr) = self.expression()? {
-    //         content.push(expr);
-
-    //         if self.current_token == Some(Token::Semicolon) {
-    //             self.current_token = self.lexer.next();
-    //         } else {
-    //             break;
-    //         }
-    //     }
-
-    //     Ok(Statement::Pause { content })
-    // }
-
-    // fn print(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let mut content = Vec::new();
-
-    //     while let Some(expr) = self.expression()? {
-    //         content.push(expr);
-
-    //         if self.current_token == Some(Token::Semicolon) {
-    //             self.current_token = self.lexer.next();
-    //         } else {
-    //             break;
-    //         }
-    //     }
-
-    //     Ok(Statement::Print { content })
-    // }
-
-    // fn input(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let prompt = self.expression()?;
-
-    //     if self.current_token == Some(Token::Semicolon) {
-    //         self.current_token = self.lexer.next();
-    //     }
-
-    //     let variable = match self.current_token {
-    //         Some(Token::Identifier(_)) => self.lvalue()?,
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedIdentifier,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-
-    //     Ok(Statement::Input { prompt, variable })
-    // }
-
-    // fn wait(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let time = self.expression()?;
-
-    //     Ok(Statement::Wait { time })
-    // }
-
-    // fn data(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let mut values = Vec::new();
-
-    //     loop {
-    //         match &mut self.current_token {
-    //             Some(Token::Number(n)) => {
-    //                 values.push(DataItem::Number(*n));
-    //                 self.current_token = self.lexer.next();
-    //             }
-    //             Some(Token::String(s)) => {
-    //                 values.push(DataItem::String(std::mem::take(s)));
-    //                 self.current_token = self.lexer.next();
-    //             }
-    //             _ => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedDataItem,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         }
-
-    //         if self.current_token == Some(Token::Comma) {
-    //             self.current_token = self.lexer.next();
-    //         } else {
-    //             break;
-    //         }
-    //     }
-
-    //     Ok(Statement::Data { values })
-    // }
-
-    // fn read(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let mut variables = Vec::new();
-
-    //     loop {
-    //         match self.current_token {
-    //             Some(Token::Identifier(_)) => {
-    //                 variables.push(self.lvalue()?);
-    //                 self.current_token = self.lexer.next();
-    //             }
-    //             _ => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedIdentifier,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         }
-
-    //         if self.current_token == Some(Token::Comma) {
-    //             self.current_token = self.lexer.next();
-    //         } else {
-    //             break;
-    //         }
-    //     }
-
-    //     Ok(Statement::Read { variables })
-    // }
-
-    // fn restore(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let line_number = match &self.current_token {
-    //         Some(Token::Number(n)) => match u32::try_from(*n) {
-    //             Ok(n) => Some(n),
-    //             Err(_) => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedUnsigned,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         },
-    //         _ => None,
-    //     };
-
-    //     if line_number.is_some() {
-    //         self.current_token = self.lexer.next();
-    //     }
-
-    //     Ok(Statement::Restore { line_number })
-    // }
-
-    // fn poke(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let address = match &self.current_token {
-    //         Some(Token::Number(n)) => u32::try_from(*n).map_err(|_e| Error {
-    //             kind: ErrorKind::ExpectedUnsigned,
-    //             line: self.lexer.current_line(),
-    //         })?,
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedUnsigned,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-    //     if self.current_token != Some(Token::Comma) {
-    //         return Err(Error {
-    //             kind: ErrorKind::UnexpectedToken,
-    //             line: self.lexer.current_line(),
-    //         });
-    //     }
-
-    //     self.current_token = self.lexer.next();
-    //     let mut values: Vec<u8> = Vec::new();
-
-    //     loop {
-    //         match &mut self.current_token {
-    //             Some(Token::Number(n)) => {
-    //                 values.push(u8::try_from(*n).map_err(|_e| Error {
-    //                     kind: ErrorKind::ExpectedUnsigned,
-    //                     line: self.lexer.current_line(),
-    //                 })?);
-    //                 self.current_token = self.lexer.next();
-    //             }
-    //             _ => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedUnsigned,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         }
-
-    //         if self.current_token == Some(Token::Comma) {
-    //             self.current_token = self.lexer.next();
-    //         } else {
-    //             break;
-    //         }
-    //     }
-
-    //     Ok(Statement::Poke { address, values })
-    // }
-
-    // fn call(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let address = match &self.current_token {
-    //         Some(Token::Number(n)) => u32::try_from(*n).map_err(|_e| Error {
-    //             kind: ErrorKind::ExpectedUnsigned,
-    //             line: self.lexer.current_line(),
-    //         })?,
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedUnsigned,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-
-    //     Ok(Statement::Call { address })
-    // }
-
-    // fn goto(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let line_number = match &self.current_token {
-    //         Some(Token::Number(n)) => match u32::try_from(*n) {
-    //             Ok(n) => n,
-    //             Err(_) => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedUnsigned,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         },
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedUnsigned,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-
-    //     Ok(Statement::Goto { line_number })
-    // }
-
-    // fn gosub(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let line_number = match &self.current_token {
-    //         Some(Token::Number(n)) => match u32::try_from(*n) {
-    //             Ok(n) => n,
-    //             Err(_) => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedUnsigned,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         },
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedUnsigned,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-
-    //     Ok(Statement::GoSub { line_number })
-    // }
-
-    // fn return_(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-
-    //     Ok(Statement::Return)
-    // }
-
-    // fn if_(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let condition = match self.expression()? {
-    //         Some(expr) => expr,
-    //         None => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedExpression,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     if self.current_token == Some(Token::Then) {
-    //         self.current_token = self.lexer.next();
-    //     }
-
-    //     let then = Box::new(self.statement()?);
-
-    //     let else_ = if self.current_token == Some(Token::Else) {
-    //         self.current_token = self.lexer.next();
-    //         let statement = self.statement()?;
-    //         Some(Box::new(statement))
-    //     } else {
-    //         None
-    //     };
-
-    //     Ok(Statement::If {
-    //         condition,
-    //         then,
-    //         else_,
-    //     })
-    // }
-
-    // fn for_(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let variable = match &mut self.current_token {
-    //         Some(Token::Identifier(v)) => mem::take(v),
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedIdentifier,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-    //     if self.current_token != Some(Token::Equal) {
-    //         return Err(Error {
-    //             kind: ErrorKind::UnexpectedToken,
-    //             line: self.lexer.current_line(),
-    //         });
-    //     }
-
-    //     self.current_token = self.lexer.next();
-    //     let from = match self.expression()? {
-    //         Some(expr) => expr,
-    //         None => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedExpression,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     if self.current_token != Some(Token::To) {
-    //         return Err(Error {
-    //             kind: ErrorKind::UnexpectedToken,
-    //             line: self.lexer.current_line(),
-    //         });
-    //     }
-
-    //     self.current_token = self.lexer.next();
-    //     let to = match self.expression()? {
-    //         Some(expr) => expr,
-    //         None => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedExpression,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     let step = if self.current_token == Some(Token::Step) {
-    //         self.current_token = self.lexer.next();
-    //         match self.expression()? {
-    //             Some(expr) => Some(expr),
-    //             None => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedExpression,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         }
-    //     } else {
-    //         None
-    //     };
-
-    //     Ok(Statement::For {
-    //         variable,
-    //         from,
-    //         to,
-    //         step,
-    //     })
-    // }
-
-    // fn next(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let variable = match &mut self.current_token {
-    //         Some(Token::Identifier(v)) => mem::take(v),
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedIdentifier,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-
-    //     Ok(Statement::Next { variable })
-    // }
-
-    // fn end(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-
-    //     Ok(Statement::End)
-    // }
-
-    // fn comment(&mut self) -> Result<Statement, Error> {
-    //     match &mut self.current_token {
-    //         Some(Token::Rem(s)) => {
-    //             let res = Ok(Statement::Rem {
-    //                 content: mem::take(s),
-    //             });
-
-    //             self.current_token = self.lexer.next();
-
-    //             res
-    //         }
-    //         _ => {
-    //             unreachable!("We already checked for REM");
-    //         }
-    //     }
-    // }
-
-    // fn dim(&mut self) -> Result<Statement, Error> {
-    //     self.current_token = self.lexer.next();
-    //     let variable = match &mut self.current_token {
-    //         Some(Token::Identifier(v)) => mem::take(v),
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedIdentifier,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-    //     if self.current_token != Some(Token::LeftParen) {
-    //         return Err(Error {
-    //             kind: ErrorKind::ExpectedLeftParen,
-    //             line: self.lexer.current_line(),
-    //         });
-    //     }
-
-    //     self.current_token = self.lexer.next();
-    //     let size = match &self.current_token {
-    //         Some(Token::Number(n)) => match u32::try_from(*n) {
-    //             Ok(n) => n,
-    //             Err(_) => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedUnsigned,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         },
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedUnsigned,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     };
-
-    //     if self.current_token != Some(Token::RightParen) {
-    //         return Err(Error {
-    //             kind: ErrorKind::ExpectedRightParen,
-    //             line: self.lexer.current_line(),
-    //         });
-    //     }
-
-    //     self.current_token = self.lexer.next();
-
-    //     let length = if self.current_token == Some(Token::Star) {
-    //         self.current_token = self.lexer.next();
-    //         match &self.current_token {
-    //             Some(Token::Number(n)) => match u32::try_from(*n) {
-    //                 Ok(n) => {
-    //                     self.current_token = self.lexer.next();
-    //                     Some(n)
-    //                 }
-    //                 Err(_) => {
-    //                     return Err(Error {
-    //                         kind: ErrorKind::ExpectedUnsigned,
-    //                         line: self.lexer.current_line(),
-    //                     });
-    //                 }
-    //             },
-    //             _ => {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedUnsigned,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         }
-    //     } else {
-    //         None
-    //     };
-
-    //     Ok(Statement::Dim {
-    //         variable,
-    //         size,
-    //         length,
-    //     })
-    // }
-
-    // fn atomic_statement(&mut self) -> Result<Statement, Error> {
-    //     // println!("Atomic statement: {:?}", self.current_token);
-    //     match self.current_token {
-    //         Some(Token::Let | Token::Identifier(_)) => self.let_(),
-    //         Some(Token::Print) => self.print(),
-    //         Some(Token::Pause) => self.pause(),
-    //         Some(Token::Input) => self.input(),
-    //         Some(Token::Wait) => self.wait(),
-    //         Some(Token::Goto) => self.goto(),
-    //         Some(Token::For) => self.for_(),
-    //         Some(Token::Next) => self.next(),
-    //         Some(Token::End) => self.end(),
-    //         Some(Token::Gosub) => self.gosub(),
-    //         Some(Token::If) => self.if_(),
-    //         Some(Token::Return) => self.return_(),
-    //         Some(Token::Data) => self.data(),
-    //         Some(Token::Read) => self.read(),
-    //         Some(Token::Restore) => self.restore(),
-    //         Some(Token::Poke) => self.poke(),
-    //         Some(Token::Call) => self.call(),
-    //         Some(Token::Dim) => self.dim(),
-    //         Some(Token::Rem(_)) => self.comment(),
-    //         _ => Err(Error {
-    //             kind: ErrorKind::ExpectedStatement,
-    //             line: self.lexer.current_line(),
-    //         }),
-    //     }
-    // }
-
-    // fn statement(&mut self) -> Result<Statement, Error> {
-    //     // TODO: small vec optimization
-    //     let mut statements = Vec::new();
-
-    //     loop {
-    //         let stmt = self.atomic_statement()?;
-
-    //         statements.push(stmt);
-
-    //         if self.current_token == Some(Token::Colon) {
-    //             self.current_token = self.lexer.next();
-    //         } else {
-    //             break;
-    //         }
-    //     }
-
-    //     Ok(if statements.len() == 1 {
-    //         statements.remove(0)
-    //     } else {
-    //         Statement::Seq { statements }
-    //     })
-    // }
-
-    // fn line(&mut self) -> Result<(u32, Statement), Error> {
-    //     let line_number = match &self.current_token {
-    //         Some(Token::Number(n)) => {
-    //             if let Ok(n) = u32::try_from(*n) {
-    //                 n
-    //             } else {
-    //                 return Err(Error {
-    //                     kind: ErrorKind::ExpectedLineNumber,
-    //                     line: self.lexer.current_line(),
-    //                 });
-    //             }
-    //         }
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedLineNumber,
-    //                 line: self.lexer.current_line(),
-    //             })
-    //         }
-    //     };
-
-    //     self.current_token = self.lexer.next();
-    //     let statement = self.statement()?;
-
-    //     match self.current_token {
-    //         Some(Token::Newline) => {
-    //             self.current_token = self.lexer.next();
-    //         }
-    //         None => {}
-    //         _ => {
-    //             return Err(Error {
-    //                 kind: ErrorKind::ExpectedEndOfLine,
-    //                 line: self.lexer.current_line(),
-    //             });
-    //         }
-    //     }
-
-    //     Ok((line_number, statement))
-    // }
-
-    // fn program(&mut self) -> (Program, Vec<Error>) {
-    //     let mut errors = Vec::new();
-    //     let mut program = Program::new();
-
-    //     self.current_token = self.lexer.next();
-
-    //     while self.current_token.is_some() {
-    //         match self.line() {
-    //             Ok((line_number, statement)) => {
-    //                 program.add_line(line_number, statement);
-    //             }
-    //             Err(e) => {
-    //                 errors.push(e);
-    //                 self.current_token = self.lexer.next();
-
-    //                 while self.current_token != Some(Token::Newline) {
-    //                     self.current_token = self.lexer.next();
-    //                 }
-    //             }
-    //         }
-    //     }
-
-    //     (program, errors)
-    // }
+        // Blank lines and unnumbered comments leave nothing to parse
+        while self
+            .lexer()
+            .next_if(|token| matches!(token, Token::Newline | Token::Rem(_)))
+            .is_some()
+        {}
+
+        self.program()
+    }
+
+    fn lexer(&mut self) -> &mut Peekable<Lexer<'a>> {
+        self.expression_parser.lexer()
+    }
+
+    fn error(&mut self, kind: ErrorKind) -> Error {
+        self.expression_parser.error(kind)
+    }
+
+    fn expect(&mut self, token: Token, kind: ErrorKind) -> Result<(), Error> {
+        match self.lexer().next_if_eq(&token) {
+            Some(_) => Ok(()),
+            None => Err(self.error(kind)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Option<Expression>, Error> {
+        self.expression_parser.parse()
+    }
+
+    fn required_expression(&mut self) -> Result<Expression, Error> {
+        match self.expression()? {
+            Some(expression) => Ok(expression),
+            None => Err(self.error(ErrorKind::ExpectedExpression)),
+        }
+    }
+
+    // Expressions separated by semicolons, as in PRINT and PAUSE
+    fn expression_list(&mut self) -> Result<Vec<Expression>, Error> {
+        let mut content = Vec::new();
+
+        while let Some(expr) = self.expression()? {
+            content.push(expr);
+
+            if self.lexer().next_if_eq(&Token::Semicolon).is_none() {
+                break;
+            }
+        }
+
+        Ok(content)
+    }
+
+    fn print_items(&mut self) -> Result<Vec<(Expression, Separator)>, Error> {
+        let mut content = Vec::new();
+
+        while let Some(expr) = self.expression()? {
+            let separator = match self
+                .lexer()
+                .next_if(|token| matches!(token, Token::Semicolon | Token::Comma))
+            {
+                Some(Token::Comma) => Separator::Comma,
+                Some(_) => Separator::Semicolon,
+                None => Separator::None,
+            };
+            content.push((expr, separator));
+
+            if separator == Separator::None {
+                break;
+            }
+        }
+
+        Ok(content)
+    }
+
+    fn identifier(&mut self) -> Result<String, Error> {
+        match self
+            .lexer()
+            .next_if(|token| matches!(token, Token::Identifier(_)))
+        {
+            Some(Token::Identifier(v)) => Ok(v),
+            _ => Err(self.error(ErrorKind::ExpectedIdentifier)),
+        }
+    }
+
+    fn lvalue(&mut self) -> Result<LValue, Error> {
+        self.expression_parser.lvalue()
+    }
+
+    // A number literal that fits in T, line numbers, addresses and sizes
+    fn unsigned<T: TryFrom<i64>>(&mut self, kind: ErrorKind) -> Result<T, Error> {
+        match self
+            .lexer()
+            .next_if(|token| matches!(token, Token::Number(_)))
+        {
+            Some(Token::Number(n)) => {
+                T::try_from(n).map_err(|_e| self.error(ErrorKind::NumberOutOfRange))
+            }
+            _ => Err(self.error(kind)),
+        }
+    }
+
+    fn let_(&mut self) -> Result<Statement, Error> {
+        // Optional LET keyword
+        self.lexer().next_if_eq(&Token::Let);
+
+        let variable = self.lvalue()?;
+        self.expect(Token::Equal, ErrorKind::UnexpectedToken)?;
+        let expression = self.required_expression()?;
+
+        Ok(assignment(self.dialect, variable, expression))
+    }
+
+    fn pause(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let content = self.expression_list()?;
+
+        Ok(Statement::Pause { content })
+    }
+
+    fn print(&mut self) -> Result<Statement, Error> {
+        let device = if self.lexer().next() == Some(Token::LPrint) {
+            OutputDevice::Printer
+        } else {
+            OutputDevice::Display
+        };
+
+        // PRINT ; A continues on the current display line
+        let continue_line = self.lexer().next_if_eq(&Token::Semicolon).is_some();
+        let mut content = self.print_items()?;
+
+        // A trailing ; keeps the cursor on the line, a trailing , is dropped
+        let suppress_newline = match content.last_mut() {
+            Some((_, separator)) => {
+                mem::replace(separator, Separator::None) == Separator::Semicolon
+            }
+            None => false,
+        };
+
+        Ok(Statement::Print {
+            content,
+            continue_line,
+            suppress_newline,
+            device,
+        })
+    }
+
+    fn input(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+
+        // INPUT #n, reads from a device instead of the keyboard
+        let device = if self.lexer().next_if_eq(&Token::Hash).is_some() {
+            let device = self.required_expression()?;
+            self.expect(Token::Comma, ErrorKind::UnexpectedToken)?;
+            Some(device)
+        } else {
+            None
+        };
+
+        // A prompt is a string, a bare variable is the target
+        let prompt = if matches!(self.lexer().peek(), Some(Token::Identifier(_))) {
+            None
+        } else {
+            let prompt = self.expression()?;
+            self.lexer().next_if_eq(&Token::Semicolon);
+            prompt
+        };
+
+        let variable = self.lvalue()?;
+
+        Ok(Statement::Input {
+            device,
+            prompt,
+            variable,
+        })
+    }
+
+    fn wait(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let time = self.expression()?;
+
+        Ok(Statement::Wait { time })
+    }
+
+    fn clear(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let size = self.expression()?;
+
+        Ok(Statement::Clear { size })
+    }
+
+    fn chain(&mut self) -> Result<Statement, Error> {
+        if self.dialect != Dialect::Generic {
+            return Err(self.error(ErrorKind::NotInDialect));
+        }
+
+        self.lexer().next();
+        let name = self.required_expression()?;
+
+        Ok(Statement::Chain { name })
+    }
+
+    fn data(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let mut values = Vec::new();
+
+        loop {
+            // A sign is only allowed in front of a number
+            let sign = self
+                .lexer()
+                .next_if(|token| matches!(token, Token::Plus | Token::Minus));
+
+            let item = self.lexer().next_if(|token| {
+                matches!(token, Token::Number(_) | Token::Float(_) | Token::String(_))
+            });
+
+            match (sign, item) {
+                (sign, Some(Token::Number(n))) => {
+                    let (value, raw) = match sign {
+                        Some(Token::Minus) => (-n, format!("-{}", n)),
+                        Some(_) => (n, format!("+{}", n)),
+                        None => (n, n.to_string()),
+                    };
+
+                    values.push(DataItem::Number {
+                        value: i32::try_from(value)
+                            .map_err(|_e| self.error(ErrorKind::NumberOutOfRange))?,
+                        raw,
+                    });
+                }
+                (sign, Some(Token::Float(n))) => {
+                    let sign = match sign {
+                        Some(Token::Minus) => "-",
+                        Some(_) => "+",
+                        None => "",
+                    };
+
+                    // Reading the value back from the spelling applies the sign
+                    let raw = format!("{}{}", sign, format_float(n));
+                    let value = raw
+                        .parse()
+                        .map_err(|_e| self.error(ErrorKind::ExpectedDataItem))?;
+                    if !expression::is_in_range(value) {
+                        return Err(self.error(ErrorKind::NumberOutOfRange));
+                    }
+
+                    values.push(DataItem::Real { value, raw });
+                }
+                (None, Some(Token::String(s))) => values.push(DataItem::String(s)),
+                _ => return Err(self.error(ErrorKind::ExpectedDataItem)),
+            }
+
+            if self.lexer().next_if_eq(&Token::Comma).is_none() {
+                break;
+            }
+        }
+
+        Ok(Statement::Data { values })
+    }
+
+    fn read(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let mut variables = vec![self.lvalue()?];
+
+        while self.lexer().next_if_eq(&Token::Comma).is_some() {
+            variables.push(self.lvalue()?);
+        }
+
+        Ok(Statement::Read { variables })
+    }
+
+    fn restore(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let line_number = if matches!(self.lexer().peek(), Some(Token::Number(_))) {
+            Some(self.unsigned(ErrorKind::ExpectedUnsigned)?)
+        } else {
+            None
+        };
+
+        Ok(Statement::Restore { line_number })
+    }
+
+    fn poke(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let address = self.unsigned(ErrorKind::ExpectedUnsigned)?;
+        self.expect(Token::Comma, ErrorKind::UnexpectedToken)?;
+
+        let mut values: Vec<u8> = vec![self.unsigned(ErrorKind::ExpectedUnsigned)?];
+        while self.lexer().next_if_eq(&Token::Comma).is_some() {
+            values.push(self.unsigned(ErrorKind::ExpectedUnsigned)?);
+        }
+
+        Ok(Statement::Poke { address, values })
+    }
+
+    fn call(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let address = self.unsigned(ErrorKind::ExpectedUnsigned)?;
+
+        Ok(Statement::Call { address })
+    }
+
+    fn goto(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let line_number = self.unsigned(ErrorKind::ExpectedUnsigned)?;
+
+        Ok(Statement::Goto { line_number })
+    }
+
+    fn gosub(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let line_number = self.unsigned(ErrorKind::ExpectedUnsigned)?;
+
+        Ok(Statement::GoSub { line_number })
+    }
+
+    fn on(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let selector = self.required_expression()?;
+        let gosub = match self
+            .lexer()
+            .next_if(|t| matches!(t, Token::Goto | Token::Gosub))
+        {
+            Some(Token::Gosub) => true,
+            Some(_) => false,
+            None => return Err(self.error(ErrorKind::UnexpectedToken)),
+        };
+
+        let mut targets = vec![self.unsigned(ErrorKind::ExpectedUnsigned)?];
+        while self.lexer().next_if_eq(&Token::Comma).is_some() {
+            targets.push(self.unsigned(ErrorKind::ExpectedUnsigned)?);
+        }
+
+        Ok(if gosub {
+            Statement::OnGosub { selector, targets }
+        } else {
+            Statement::OnGoto { selector, targets }
+        })
+    }
+
+    fn return_(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+
+        Ok(Statement::Return)
+    }
+
+    fn if_(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let condition = self.required_expression()?;
+
+        self.lexer().next_if_eq(&Token::Then);
+
+        let then = Box::new(self.statement()?);
+
+        let else_ = if self.lexer().next_if_eq(&Token::Else).is_some() {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Statement::If {
+            condition,
+            then,
+            else_,
+        })
+    }
+
+    fn for_(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let variable = self.identifier()?;
+
+        self.expect(Token::Equal, ErrorKind::UnexpectedToken)?;
+        let from = self.required_expression()?;
+
+        self.expect(Token::To, ErrorKind::UnexpectedToken)?;
+        let to = self.required_expression()?;
+
+        let step = if self.lexer().next_if_eq(&Token::Step).is_some() {
+            Some(self.required_expression()?)
+        } else {
+            None
+        };
+
+        Ok(Statement::For {
+            variable,
+            from,
+            to,
+            step,
+        })
+    }
+
+    fn next(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        // A bare NEXT closes the innermost loop
+        let variable = match self
+            .lexer()
+            .next_if(|token| matches!(token, Token::Identifier(_)))
+        {
+            Some(Token::Identifier(v)) => Some(v),
+            _ => None,
+        };
+
+        Ok(Statement::Next { variable })
+    }
+
+    fn end(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+
+        Ok(Statement::End)
+    }
+
+    fn stop(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+
+        Ok(Statement::Stop)
+    }
+
+    fn comment(&mut self) -> Result<Statement, Error> {
+        match self.lexer().next() {
+            Some(Token::Rem(content)) => Ok(Statement::Rem { content }),
+            _ => {
+                unreachable!("We already checked for REM");
+            }
+        }
+    }
+
+    fn dim(&mut self) -> Result<Statement, Error> {
+        self.lexer().next();
+        let variable = self.identifier()?;
+
+        self.expect(Token::LeftParen, ErrorKind::ExpectedLeftParen)?;
+        let mut sizes = vec![self.unsigned(ErrorKind::ExpectedUnsigned)?];
+        while self.lexer().next_if_eq(&Token::Comma).is_some() {
+            sizes.push(self.unsigned(ErrorKind::ExpectedUnsigned)?);
+        }
+        self.expect(Token::RightParen, ErrorKind::ExpectedRightParen)?;
+
+        let length = if self.lexer().next_if_eq(&Token::Star).is_some() {
+            Some(self.unsigned(ErrorKind::ExpectedUnsigned)?)
+        } else {
+            None
+        };
+
+        Ok(Statement::Dim {
+            variable,
+            sizes,
+            length,
+        })
+    }
+
+    fn atomic_statement(&mut self) -> Result<Statement, Error> {
+        match self.lexer().peek() {
+            Some(Token::Let | Token::Identifier(_)) => self.let_(),
+            Some(Token::Print | Token::LPrint) => self.print(),
+            Some(Token::Pause) => self.pause(),
+            Some(Token::Input) => self.input(),
+            Some(Token::Wait) => self.wait(),
+            Some(Token::Goto) => self.goto(),
+            Some(Token::For) => self.for_(),
+            Some(Token::Next) => self.next(),
+            Some(Token::End) => self.end(),
+            Some(Token::Stop) => self.stop(),
+            Some(Token::Gosub) => self.gosub(),
+            Some(Token::On) => self.on(),
+            Some(Token::If) => self.if_(),
+            Some(Token::Return) => self.return_(),
+            Some(Token::Data) => self.data(),
+            Some(Token::Read) => self.read(),
+            Some(Token::Restore) => self.restore(),
+            Some(Token::Poke) => self.poke(),
+            Some(Token::Call) => self.call(),
+            Some(Token::Dim) => self.dim(),
+            Some(Token::Clear) => self.clear(),
+            Some(Token::Chain) => self.chain(),
+            Some(Token::Rem(_)) => self.comment(),
+            _ => Err(self.error(ErrorKind::ExpectedStatement)),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, Error> {
+        // TODO: small vec optimization
+        let mut statements = vec![self.atomic_statement()?];
+
+        while self.lexer().next_if_eq(&Token::Colon).is_some() {
+            statements.push(self.atomic_statement()?);
+        }
+
+        Ok(if statements.len() == 1 {
+            statements.remove(0)
+        } else {
+            Statement::Seq { statements }
+        })
+    }
+
+    fn line(&mut self) -> Result<(u32, Statement), Error> {
+        let line_number = self.unsigned(ErrorKind::ExpectedLineNumber)?;
+
+        // In direct mode a bare line number deletes the line, in a file it is
+        // a mistake
+        if matches!(self.lexer().peek(), Some(Token::Newline) | None) {
+            return Err(self.error(ErrorKind::ExpectedStatement));
+        }
+
+        let statement = self.statement()?;
+
+        // The last line does not need a line break
+        match self.lexer().next_if_eq(&Token::Newline) {
+            Some(_) => Ok((line_number, statement)),
+            None if self.lexer().peek().is_none() => Ok((line_number, statement)),
+            None => Err(self.error(ErrorKind::ExpectedEndOfLine)),
+        }
+    }
+
+    fn program(&mut self) -> (Program, Vec<Error>) {
+        let mut errors = Vec::new();
+        let mut program = Program::new();
+
+        while let Some(next) = self.lexer().peek() {
+            if *next == Token::Newline {
+                self.lexer().next();
+                continue;
+            }
+
+            match self.line() {
+                Ok((line_number, statement)) => {
+                    program.add_line(line_number, statement);
+                }
+                Err(e) => {
+                    errors.push(e);
+
+                    // Skip to the next line
+                    while self
+                        .lexer()
+                        .next_if(|token| *token != Token::Newline)
+                        .is_some()
+                    {}
+                }
+            }
+        }
+
+        (program, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Printer;
+
+    fn parse(input: &str) -> (Program, Vec<Error>) {
+        Parser::new(Lexer::new(input)).parse()
+    }
+
+    fn parse_ok(dialect: Dialect, input: &str) -> Program {
+        let (program, errors) = Parser::new(Lexer::new(input)).dialect(dialect).parse();
+        assert!(errors.is_empty(), "unexpected errors: {}", errors.len());
+        program
+    }
+
+    fn round_trip(input: &str) -> String {
+        Printer::new().build(&parse_ok(Dialect::Pc1500, input))
+    }
+
+    fn let_of(dialect: Dialect, input: &str) -> Statement {
+        let mut parser = ExpressionParser::new(Lexer::new(input));
+        let expression = parser.parse().unwrap().unwrap();
+        assignment(dialect, LValue::Variable("A".to_owned()), expression)
+    }
+
+    fn variable(name: &str) -> LValue {
+        LValue::Variable(name.to_owned())
+    }
+
+    #[test]
+    fn chained_let_is_comparison_on_pc1500() {
+        assert_eq!(
+            let_of(Dialect::Pc1500, "B = 0"),
+            Statement::Let {
+                variable: variable("A"),
+                expression: Expression::Binary {
+                    left: Box::new(Expression::LValue(variable("B"))),
+                    op: BinaryOperator::Eq,
+                    right: Box::new(Expression::Number(0)),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn chained_let_assigns_all_in_generic() {
+        assert_eq!(
+            let_of(Dialect::Generic, "B = 0"),
+            Statement::Seq {
+                statements: vec![
+                    Statement::Let {
+                        variable: variable("B"),
+                        expression: Expression::Number(0),
+                    },
+                    Statement::Let {
+                        variable: variable("A"),
+                        expression: Expression::LValue(variable("B")),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn chained_let_three_targets() {
+        assert_eq!(
+            let_of(Dialect::Generic, "B = C = 0"),
+            Statement::Seq {
+                statements: vec![
+                    Statement::Let {
+                        variable: variable("C"),
+                        expression: Expression::Number(0),
+                    },
+                    Statement::Let {
+                        variable: variable("B"),
+                        expression: Expression::LValue(variable("C")),
+                    },
+                    Statement::Let {
+                        variable: variable("A"),
+                        expression: Expression::LValue(variable("B")),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn chained_let_needs_variables() {
+        let statement = let_of(Dialect::Generic, "B = C + 1 = 0");
+        assert!(matches!(statement, Statement::Let { .. }));
+    }
+
+    #[test]
+    fn empty_input() {
+        let (program, errors) = parse("");
+        assert!(program.is_empty());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn only_newlines() {
+        let (program, errors) = parse("\n\n  \r\n\n");
+        assert!(program.is_empty());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn only_comment() {
+        let (program, errors) = parse("REM nothing to see here\n");
+        assert!(program.is_empty());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn multi_line_program() {
+        let program = parse_ok(Dialect::Pc1500, "10 LET A = 1\n20 PRINT A\n30 END\n");
+
+        let mut expected = Program::new();
+        expected.add_line(
+            10,
+            Statement::Let {
+                variable: variable("A"),
+                expression: Expression::Number(1),
+            },
+        );
+        expected.add_line(
+            20,
+            Statement::Print {
+                content: vec![(Expression::LValue(variable("A")), Separator::None)],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        );
+        expected.add_line(30, Statement::End);
+
+        assert_eq!(program.lines, expected.lines);
+    }
+
+    #[test]
+    fn colon_builds_seq_without_final_newline() {
+        let program = parse_ok(Dialect::Pc1500, "10 A = 1: GOTO 10");
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Seq {
+                statements: vec![
+                    Statement::Let {
+                        variable: variable("A"),
+                        expression: Expression::Number(1),
+                    },
+                    Statement::Goto { line_number: 10 },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn if_then_seq_else() {
+        assert_eq!(
+            round_trip("10 IF A THEN PRINT 1: PRINT 2 ELSE END"),
+            "10 IF A THEN PRINT 1: PRINT 2 ELSE END\n"
+        );
+    }
+
+    #[test]
+    fn if_then_seq_else_structure() {
+        let program = parse_ok(Dialect::Pc1500, "10 IF A THEN X=1 : Y=2 ELSE Z=3\n");
+        let assign = |name: &str, value| Statement::Let {
+            variable: variable(name),
+            expression: Expression::Number(value),
+        };
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::If {
+                condition: Expression::LValue(variable("A")),
+                then: Box::new(Statement::Seq {
+                    statements: vec![assign("X", 1), assign("Y", 2)],
+                }),
+                else_: Some(Box::new(assign("Z", 3))),
+            })
+        );
+    }
+
+    #[test]
+    fn round_trip_statements() {
+        let input = r#"10 DIM B$(3) * 8
+20 FOR I = 1 TO 3 STEP 1
+30 INPUT "NAME"; B$(I)
+40 NEXT I
+50 DATA 1, "x"
+60 READ A, C
+70 RESTORE 50
+80 POKE 30720, 1, 2
+90 CALL 30720
+100 GOSUB 200
+110 LPRINT ; A; C
+120 WAIT 10: PAUSE A
+130 CLEAR
+140 STOP: GOTO 100
+200 RETURN
+"#;
+
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn next_variable() {
+        let program = parse_ok(Dialect::Pc1500, "10 NEXT\n20 NEXT I\n");
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Next { variable: None })
+        );
+        assert_eq!(
+            program.lookup_line(20),
+            Some(&Statement::Next {
+                variable: Some("I".to_owned())
+            })
+        );
+    }
+
+    #[test]
+    fn input_device() {
+        let program = parse_ok(Dialect::Pc1500, "10 INPUT #2, A$\n20 INPUT A$\n");
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Input {
+                device: Some(Expression::Number(2)),
+                prompt: None,
+                variable: variable("A$"),
+            })
+        );
+        assert_eq!(
+            program.lookup_line(20),
+            Some(&Statement::Input {
+                device: None,
+                prompt: None,
+                variable: variable("A$"),
+            })
+        );
+        assert_eq!(round_trip("10 INPUT #2, A$\n"), "10 INPUT #2, A$\n");
+    }
+
+    #[test]
+    fn print_separators() {
+        let program = parse_ok(Dialect::Pc1500, "10 PRINT A, B; C\n");
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Print {
+                content: vec![
+                    (Expression::LValue(variable("A")), Separator::Comma),
+                    (Expression::LValue(variable("B")), Separator::Semicolon),
+                    (Expression::LValue(variable("C")), Separator::None),
+                ],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            })
+        );
+        assert_eq!(round_trip("10 PRINT A, B; C\n"), "10 PRINT A, B; C\n");
+    }
+
+    #[test]
+    fn leading_semicolon_continues_line() {
+        let program = parse_ok(Dialect::Pc1500, "10 PRINT ; A\n20 PRINT A\n");
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Print {
+                content: vec![(Expression::LValue(variable("A")), Separator::None)],
+                continue_line: true,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            })
+        );
+        assert!(matches!(
+            program.lookup_line(20),
+            Some(Statement::Print {
+                continue_line: false,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn trailing_semicolon_suppresses_newline() {
+        let program = parse_ok(Dialect::Pc1500, "10 PRINT \"X\";\n20 PRINT A,\n");
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Print {
+                content: vec![(Expression::String("X".to_owned()), Separator::None)],
+                continue_line: false,
+                suppress_newline: true,
+                device: OutputDevice::Display,
+            })
+        );
+        assert!(matches!(
+            program.lookup_line(20),
+            Some(Statement::Print {
+                suppress_newline: false,
+                ..
+            })
+        ));
+        assert_eq!(round_trip("10 PRINT A; \"X\";\n"), "10 PRINT A; \"X\";\n");
+    }
+
+    #[test]
+    fn max_depth_limits_statements() {
+        let input = "10 A = ((1))\n";
+        let (_, errors) = Parser::new(Lexer::new(input)).max_depth(2).parse();
+
+        assert_eq!(
+            errors.iter().map(|error| error.kind).collect::<Vec<_>>(),
+            vec![ErrorKind::ExpressionTooDeep]
+        );
+        assert!(Parser::new(Lexer::new(input))
+            .max_depth(3)
+            .parse()
+            .1
+            .is_empty());
+    }
+
+    #[test]
+    fn two_dimensional_array() {
+        let input = "10 DIM A(3,4)\n20 LET A(I,J) = A(J,I)\n";
+        let program = parse_ok(Dialect::Pc1500, input);
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Dim {
+                variable: "A".to_owned(),
+                sizes: vec![3, 4],
+                length: None,
+            })
+        );
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn builtin_function_round_trip() {
+        let input =
+            "10 LET B$ = LEFT$(A$,LEN(A$))\n20 PRINT CHR$(ASC(B$))\n30 LET C = VAL(STR$(C))\n";
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn negative_base_round_trip() {
+        let input = "10 LET A = ((-2) ^ 2)\n20 LET B = -(2 ^ 2)\n";
+        assert_eq!(round_trip("10 A = (-2)^2\n20 B = -2^2\n"), input);
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn not_round_trip() {
+        let input = "10 IF NOT A THEN PRINT -B\n";
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn comment_line() {
+        assert_eq!(round_trip("10 REM hello\n20 END"), "10 REM hello\n20 END\n");
+    }
+
+    #[test]
+    fn chained_let_through_parser() {
+        let program = parse_ok(Dialect::Generic, "10 A = B = 0");
+
+        assert!(matches!(
+            program.lookup_line(10),
+            Some(Statement::Seq { statements }) if statements.len() == 2
+        ));
+    }
+
+    #[test]
+    fn bare_line_number() {
+        let (program, errors) = parse("10\n20 END\n");
+
+        assert!(matches!(
+            errors.as_slice(),
+            [Error {
+                kind: ErrorKind::ExpectedStatement,
+                ..
+            }]
+        ));
+        assert_eq!(program.lookup_line(20), Some(&Statement::End));
+    }
+
+    #[test]
+    fn error_skips_to_next_line() {
+        let (program, errors) = parse("10 GOTO X\n20 END\n");
+
+        assert!(matches!(
+            errors.as_slice(),
+            [Error {
+                kind: ErrorKind::ExpectedUnsigned,
+                ..
+            }]
+        ));
+        assert!(program.lookup_line(10).is_none());
+        assert_eq!(program.lookup_line(20), Some(&Statement::End));
+    }
+
+    #[test]
+    fn chain_needs_generic() {
+        let (_, errors) = parse("10 CHAIN \"PROG2\"");
+        assert!(matches!(
+            errors.as_slice(),
+            [Error {
+                kind: ErrorKind::NotInDialect,
+                ..
+            }]
+        ));
+
+        let program = parse_ok(Dialect::Generic, "10 CHAIN \"PROG2\"");
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Chain {
+                name: Expression::String("PROG2".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn on_goto_targets() {
+        let program = parse_ok(Dialect::Pc1500, "10 ON X GOTO 100,200,300");
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::OnGoto {
+                selector: Expression::LValue(variable("X")),
+                targets: vec![100, 200, 300],
+            })
+        );
+        assert_eq!(
+            round_trip("10 ON X+1 GOSUB 100,200"),
+            "10 ON (X + 1) GOSUB 100, 200\n"
+        );
+    }
+
+    #[test]
+    fn data_signed_numbers() {
+        let program = parse_ok(Dialect::Pc1500, "10 DATA -5, +3, \"x\"");
+
+        assert_eq!(
+            program.lookup_line(10),
+            Some(&Statement::Data {
+                values: vec![
+                    DataItem::Number {
+                        value: -5,
+                        raw: "-5".to_owned(),
+                    },
+                    DataItem::Number {
+                        value: 3,
+                        raw: "+3".to_owned(),
+                    },
+                    DataItem::String("x".to_owned()),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn float_round_trip() {
+        assert_eq!(
+            round_trip("10 A = 3.14 * 1.5E3"),
+            "10 LET A = (3.14 * 1.5E3)\n"
+        );
+    }
+
+    #[test]
+    fn data_real_numbers() {
+        assert_eq!(
+            round_trip("10 DATA -5, 3.25, -1.5E3"),
+            "10 DATA -5, 3.25, -1.5E3\n"
+        );
+    }
+
+    #[test]
+    fn data_sign_needs_number() {
+        let (_, errors) = parse("10 DATA -\"x\"");
+
+        assert!(matches!(
+            errors.as_slice(),
+            [Error {
+                kind: ErrorKind::ExpectedDataItem,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn error_reports_source_line() {
+        let (_, errors) = parse("10 A = 1\n\n30 B = 2 *\n40 END\n");
+
+        assert_eq!(
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["line 3: Expected expression"]
+        );
+    }
+
+    #[test]
+    fn unclosed_call_keeps_next_line() {
+        let (_, errors) = parse("10 PRINT LEN(\"a\"\n20 GOTO\n30 END\n");
+        let errors: Vec<String> = errors.iter().map(Error::to_string).collect();
+
+        assert_eq!(
+            errors,
+            vec!["line 1: Expected ')'", "line 2: Expected unsigned number"]
+        );
+    }
+
+    #[test]
+    fn line_number_out_of_range() {
+        let (_, errors) = parse("10 GOTO 99999999999\n20 DIM A(99999999999)\n");
+        let errors: Vec<String> = errors.iter().map(Error::to_string).collect();
+
+        assert_eq!(
+            errors,
+            vec!["line 1: Number out of range", "line 2: Number out of range"]
+        );
+    }
+
+    #[test]
+    fn errors_on_several_lines() {
+        let (_, errors) = parse("10 GOTO\r\n20 END\r\n30 FOR = 1\r\n");
+
+        assert_eq!(
+            errors.iter().map(|error| error.line).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+    }
+
+    #[test]
+    fn lex_errors_become_parse_errors() {
+        let (program, errors) = parse("10 PRINT \"abc\n20 A = 1 @ 2\n30 END");
+
+        assert_eq!(
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "line 1: String is missing its closing \"",
+                "line 2: Unexpected character '@'",
+            ]
+        );
+        assert_eq!(program.lookup_line(30), Some(&Statement::End));
+    }
 }
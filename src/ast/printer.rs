@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
 use super::{
-    node::{DataItem, LValue, UnaryOperator},
+    node::{format_float, DataItem, LValue, OutputDevice, Separator, UnaryOperator},
     Expression, ExpressionVisitor, Program, ProgramVisitor, Statement, StatementVisitor,
 };
 
@@ -23,6 +23,22 @@ impl<'a> Printer<'a> {
         ast.accept(&mut visitor);
         visitor.output
     }
+
+    pub fn build_expression(self, expression: &'a Expression) -> String {
+        let mut visitor = Printer::new();
+        expression.accept(&mut visitor);
+        visitor.output
+    }
+
+    fn on(&mut self, keyword: &str, selector: &'a Expression, targets: &'a [u32]) {
+        self.output.push_str("ON ");
+        selector.accept(self);
+        self.output.push(' ');
+        self.output.push_str(keyword);
+        self.output.push(' ');
+        let targets: Vec<String> = targets.iter().map(u32::to_string).collect();
+        self.output.push_str(&targets.join(", "));
+    }
 }
 
 impl<'a> ExpressionVisitor<'a> for Printer<'a> {
@@ -30,12 +46,19 @@ impl<'a> ExpressionVisitor<'a> for Printer<'a> {
         self.output.push_str(&num.to_string());
     }
 
+    fn visit_float_literal(&mut self, num: f64) {
+        self.output.push_str(&format_float(num));
+    }
+
     fn visit_variable(&mut self, variable: &'a LValue) {
         self.output.push_str(variable.to_string().as_str());
     }
 
     fn visit_unary_op(&mut self, op: UnaryOperator, operand: &'a Expression) {
         self.output.push_str(op.to_string().as_str());
+        if op == UnaryOperator::Not {
+            self.output.push(' ');
+        }
         operand.accept(self);
     }
 
@@ -46,7 +69,14 @@ impl<'a> ExpressionVisitor<'a> for Printer<'a> {
         right: &'a Expression,
     ) {
         self.output.push('(');
-        left.accept(self);
+        // ^ binds tighter than unary minus, (-2)^2 needs its parentheses
+        if let (Expression::Unary { .. }, super::BinaryOperator::Pow) = (left, op) {
+            self.output.push('(');
+            left.accept(self);
+            self.output.push(')');
+        } else {
+            left.accept(self);
+        }
         self.output.push(' ');
         self.output.push_str(op.to_string().as_str());
         self.output.push(' ');
@@ -59,6 +89,24 @@ impl<'a> ExpressionVisitor<'a> for Printer<'a> {
         self.output.push_str(content);
         self.output.push('"');
     }
+
+    fn visit_varptr(&mut self, operand: &'a Expression) {
+        self.output.push_str("VARPTR(");
+        operand.accept(self);
+        self.output.push(')');
+    }
+
+    fn visit_function(&mut self, name: &'a str, args: &'a [Expression]) {
+        self.output.push_str(name);
+        self.output.push('(');
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                self.output.push(',');
+            }
+            arg.accept(self);
+        }
+        self.output.push(')');
+    }
 }
 
 impl<'a> StatementVisitor<'a> for Printer<'a> {
@@ -69,13 +117,30 @@ impl<'a> StatementVisitor<'a> for Printer<'a> {
         expression.accept(self);
     }
 
-    fn visit_print(&mut self, content: &'a [Expression]) {
-        self.output.push_str("PRINT ");
-        for (i, item) in content.iter().enumerate() {
-            if i > 0 {
-                self.output.push_str("; ");
-            }
+    fn visit_print(
+        &mut self,
+        content: &'a [(Expression, Separator)],
+        continue_line: bool,
+        suppress_newline: bool,
+        device: OutputDevice,
+    ) {
+        match device {
+            OutputDevice::Display => self.output.push_str("PRINT "),
+            OutputDevice::Printer => self.output.push_str("LPRINT "),
+        }
+        if continue_line {
+            self.output.push_str("; ");
+        }
+        for (item, separator) in content {
             item.accept(self);
+            match separator {
+                Separator::Semicolon => self.output.push_str("; "),
+                Separator::Comma => self.output.push_str(", "),
+                Separator::None => {}
+            }
+        }
+        if suppress_newline {
+            self.output.push(';');
         }
     }
 
@@ -89,8 +154,18 @@ impl<'a> StatementVisitor<'a> for Printer<'a> {
         }
     }
 
-    fn visit_input(&mut self, prompt: Option<&'a Expression>, variable: &'a LValue) {
+    fn visit_input(
+        &mut self,
+        device: Option<&'a Expression>,
+        prompt: Option<&'a Expression>,
+        variable: &'a LValue,
+    ) {
         self.output.push_str("INPUT ");
+        if let Some(device) = device {
+            self.output.push('#');
+            device.accept(self);
+            self.output.push_str(", ");
+        }
         if let Some(prompt) = prompt {
             prompt.accept(self);
             self.output.push_str("; ");
@@ -129,20 +204,35 @@ impl<'a> StatementVisitor<'a> for Printer<'a> {
         }
     }
 
-    fn visit_next(&mut self, variable: &'a str) {
-        self.output.push_str("NEXT ");
-        self.output.push_str(variable);
+    fn visit_next(&mut self, variable: Option<&'a str>) {
+        self.output.push_str("NEXT");
+        if let Some(variable) = variable {
+            self.output.push(' ');
+            self.output.push_str(variable);
+        }
     }
 
     fn visit_end(&mut self) {
         self.output.push_str("END");
     }
 
+    fn visit_stop(&mut self) {
+        self.output.push_str("STOP");
+    }
+
     fn visit_gosub(&mut self, line_number: u32) {
         self.output.push_str("GOSUB ");
         self.output.push_str(&line_number.to_string());
     }
 
+    fn visit_on_goto(&mut self, selector: &'a Expression, targets: &'a [u32]) {
+        self.on("GOTO", selector, targets);
+    }
+
+    fn visit_on_gosub(&mut self, selector: &'a Expression, targets: &'a [u32]) {
+        self.on("GOSUB", selector, targets);
+    }
+
     fn visit_return(&mut self) {
         self.output.push_str("RETURN");
     }
@@ -192,10 +282,7 @@ impl<'a> StatementVisitor<'a> for Printer<'a> {
             if i > 0 {
                 self.output.push_str(", ");
             }
-            match value {
-                DataItem::Number(num) => self.output.push_str(&num.to_string()),
-                DataItem::String(string) => self.output.push_str(string),
-            }
+            self.output.push_str(value.to_string().as_str());
         }
     }
 
@@ -223,11 +310,12 @@ impl<'a> StatementVisitor<'a> for Printer<'a> {
         self.output.push_str(&address.to_string());
     }
 
-    fn visit_dim(&mut self, variable: &'a str, size: u32, length: Option<u32>) {
+    fn visit_dim(&mut self, variable: &'a str, sizes: &'a [u32], length: Option<u32>) {
         self.output.push_str("DIM ");
         self.output.push_str(variable);
         self.output.push('(');
-        self.output.push_str(&size.to_string());
+        let sizes: Vec<String> = sizes.iter().map(u32::to_string).collect();
+        self.output.push_str(&sizes.join(","));
         self.output.push(')');
 
         if let Some(length) = length {
@@ -235,15 +323,158 @@ impl<'a> StatementVisitor<'a> for Printer<'a> {
             self.output.push_str(&length.to_string());
         }
     }
+
+    fn visit_clear(&mut self, size: Option<&'a Expression>) {
+        self.output.push_str("CLEAR");
+        if let Some(size) = size {
+            self.output.push(' ');
+            size.accept(self);
+        }
+    }
+
+    fn visit_chain(&mut self, name: &'a Expression) {
+        self.output.push_str("CHAIN ");
+        name.accept(self);
+    }
 }
 
 impl<'a> ProgramVisitor<'a> for Printer<'a> {
     fn visit_program(&mut self, program: &'a Program) {
         for (line_number, ast) in program.iter() {
             self.output.push_str(&line_number.to_string());
+            self.output.push(' ');
 
             ast.accept(self);
             self.output.push('\n');
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print_continue_line() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Print {
+                content: vec![(
+                    Expression::LValue(LValue::Variable("A".to_owned())),
+                    Separator::None,
+                )],
+                continue_line: true,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        );
+        program.add_line(
+            20,
+            Statement::Print {
+                content: vec![(
+                    Expression::LValue(LValue::Variable("A".to_owned())),
+                    Separator::None,
+                )],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        );
+
+        let output = Printer::new().build(&program);
+
+        assert_eq!(output, "10 PRINT ; A\n20 PRINT A\n");
+    }
+
+    #[test]
+    fn lprint() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Print {
+                content: vec![(Expression::String("x".to_owned()), Separator::None)],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Printer,
+            },
+        );
+
+        let output = Printer::new().build(&program);
+
+        assert_eq!(output, "10 LPRINT \"x\"\n");
+    }
+
+    #[test]
+    fn input_device() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Input {
+                device: Some(Expression::Number(2)),
+                prompt: None,
+                variable: LValue::Variable("A$".to_owned()),
+            },
+        );
+
+        let output = Printer::new().build(&program);
+
+        assert_eq!(output, "10 INPUT #2, A$\n");
+    }
+
+    #[test]
+    fn clear() {
+        let mut program = Program::new();
+        program.add_line(10, Statement::Clear { size: None });
+        program.add_line(
+            20,
+            Statement::Clear {
+                size: Some(Expression::Number(100)),
+            },
+        );
+
+        let output = Printer::new().build(&program);
+
+        assert_eq!(output, "10 CLEAR\n20 CLEAR 100\n");
+    }
+
+    #[test]
+    fn chain() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Chain {
+                name: Expression::String("PROG2".to_owned()),
+            },
+        );
+
+        let output = Printer::new().build(&program);
+
+        assert_eq!(output, "10 CHAIN \"PROG2\"\n");
+    }
+
+    #[test]
+    fn data_keeps_spelling() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Data {
+                values: vec![
+                    DataItem::Number {
+                        value: 5,
+                        raw: "+5".to_owned(),
+                    },
+                    DataItem::String("x".to_owned()),
+                    DataItem::Number {
+                        value: 7,
+                        raw: "007".to_owned(),
+                    },
+                ],
+            },
+        );
+
+        let output = Printer::new().build(&program);
+
+        assert_eq!(output, "10 DATA +5, \"x\", 007\n");
+    }
+}
@@ -1,5 +1,14 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem,
+};
+
 use super::{
-    node::{LValue, UnaryOperator},
+    calls::CallGraph,
+    diagnostic::{Diagnostic, SemKind, Severity},
+    node::{DataItem, LValue, OutputDevice, Separator, UnaryOperator},
+    symbols::SymbolKind,
+    uses::VariableUses,
     BinaryOperator, Expression, ExpressionVisitor, Program, ProgramVisitor, Statement,
     StatementVisitor,
 };
@@ -7,60 +16,366 @@ use super::{
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Ty {
     Int,
+    Real,
     String,
 }
 
+impl Ty {
+    // Integers and reals mix freely
+    pub fn is_numeric(self) -> bool {
+        matches!(self, Ty::Int | Ty::Real)
+    }
+
+    // The PC-1500 keeps every number variable in floating point
+    pub fn of_variable(name: &str) -> Self {
+        if name.ends_with('$') {
+            Ty::String
+        } else {
+            Ty::Real
+        }
+    }
+}
+
+// Parameter and return types of the built-in functions
+fn signature(name: &str) -> Option<(&'static [Ty], Ty)> {
+    match name {
+        "ASC" | "LEN" => Some((&[Ty::String], Ty::Int)),
+        "CHR$" => Some((&[Ty::Int], Ty::String)),
+        "LEFT$" | "RIGHT$" => Some((&[Ty::String, Ty::Int], Ty::String)),
+        "MID$" => Some((&[Ty::String, Ty::Int, Ty::Int], Ty::String)),
+        "STR$" => Some((&[Ty::Real], Ty::String)),
+        "VAL" => Some((&[Ty::String], Ty::Real)),
+        _ => None,
+    }
+}
+
 impl std::fmt::Display for Ty {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Ty::Int => write!(f, "INT"),
+            Ty::Real => write!(f, "REAL"),
             Ty::String => write!(f, "STR"),
         }
     }
 }
 
+// Regions of the PC-1500 address space that a POKE cannot write to. RAM,
+// including the display buffer and system registers at &7600-&7FFF, is fine.
+const ROM_REGIONS: [(u32, u32, &str); 2] = [
+    (0xA000, 0xBFFF, "CE-150 ROM"),
+    (0xC000, 0xFFFF, "system ROM"),
+];
+const MAX_ADDRESS: u32 = 0xFFFF;
+
+// Rough model of variable storage. A to Z and A$ to Z$ live in a reserved
+// area, every other variable is allocated from the user's RAM.
+const NUMBER_SIZE: usize = 8;
+const DEFAULT_STRING_LENGTH: usize = 16;
+const ARRAY_HEADER_SIZE: usize = 7;
+// Bytes left for variables on a PC-1500 without a memory module
+const DEFAULT_MEMORY_BUDGET: usize = 1850;
+
 pub struct SemanticChecker<'a> {
     program: &'a Program,
-    errors: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    line: Option<usize>,
     // symbol_table: &'a SymbolTable<'a>,
-    for_stack: Vec<&'a str>,
+    // Open loops with the line of their FOR, innermost last
+    for_stack: Vec<(&'a str, usize)>,
+    // Line of the DIM of each array DIMmed so far
+    dims: HashMap<&'a str, usize>,
+    // Line of the first use of each array before any DIM of it
+    undimmed_uses: BTreeMap<&'a str, usize>,
+    allocated: HashSet<(&'a str, SymbolKind)>,
+    memory_used: usize,
+    memory_budget: usize,
+    allow_forward_refs: bool,
 }
 
 impl<'a> SemanticChecker<'a> {
     pub fn new(program: &'a Program) -> Self {
         SemanticChecker {
-            errors: Vec::new(),
+            diagnostics: Vec::new(),
+            line: None,
             for_stack: Vec::new(),
+            dims: HashMap::new(),
+            undimmed_uses: BTreeMap::new(),
+            allocated: HashSet::new(),
+            memory_used: 0,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            allow_forward_refs: false,
             program,
             // symbol_table,
         }
     }
 
-    pub fn check(mut self) -> Result<(), Vec<String>> {
+    /// Report jumps to undefined lines as warnings instead of errors, for
+    /// programs that are still being written.
+    pub fn allow_forward_refs(mut self, allow: bool) -> Self {
+        self.allow_forward_refs = allow;
+        self
+    }
+
+    /// Bytes of RAM available for variables.
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = bytes;
+        self
+    }
+
+    pub fn check(mut self) -> Vec<Diagnostic> {
         self.program.accept(&mut self);
-        if self.errors.is_empty() {
-            Ok(())
+        self.check_recursion();
+        self.check_unassigned();
+        self.diagnostics
+    }
+
+    // A subroutine that always ends up calling itself again overflows the
+    // GOSUB stack every time, one that only sometimes does may be intended
+    fn check_recursion(&mut self) {
+        let calls = CallGraph::new(self.program);
+
+        for subroutine in calls.subroutines() {
+            self.line = Some(subroutine as usize);
+
+            let always = calls.reaches(subroutine, subroutine, true);
+            let sometimes = calls.reaches(subroutine, subroutine, false);
+
+            match (always, sometimes) {
+                (true, _) => self.error(
+                    SemKind::Recursion,
+                    format!(
+                        "Subroutine at line {} always calls itself and will overflow the GOSUB stack",
+                        subroutine
+                    ),
+                ),
+                (false, true) => self.warning(
+                    SemKind::Recursion,
+                    format!(
+                        "Subroutine at line {} may call itself, deep recursion overflows the GOSUB stack",
+                        subroutine
+                    ),
+                ),
+                (false, false) => {}
+            }
+        }
+    }
+
+    // An unset variable reads as 0 or an empty string, which is usually a
+    // misspelled name rather than intended
+    fn check_unassigned(&mut self) {
+        let uses = VariableUses::new(self.program);
+        let written: HashSet<&str> = uses
+            .iter()
+            .flat_map(|(_, line)| line.writes.iter().map(String::as_str))
+            .collect();
+        let mut reported = HashSet::new();
+
+        for (&line_number, line) in uses.iter() {
+            for variable in &line.reads {
+                if written.contains(variable.as_str()) || !reported.insert(variable) {
+                    continue;
+                }
+
+                let value = if variable.ends_with('$') {
+                    "an empty string"
+                } else {
+                    "0"
+                };
+                self.line = Some(line_number as usize);
+                self.warning(
+                    SemKind::Unassigned,
+                    format!("{} is never assigned, it is always {}", variable, value),
+                );
+            }
+        }
+    }
+
+    fn error(&mut self, kind: SemKind, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            kind,
+            message,
+            line: self.line,
+        });
+    }
+
+    fn hint(&mut self, kind: SemKind, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Hint,
+            kind,
+            message,
+            line: self.line,
+        });
+    }
+
+    fn warning(&mut self, kind: SemKind, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            kind,
+            message,
+            line: self.line,
+        });
+    }
+
+    fn check_on(&mut self, selector: &'a Expression, statement: &str, targets: &[u32]) {
+        if !selector.accept(self).is_numeric() {
+            self.error(
+                SemKind::TypeMismatch,
+                format!("{} selector must be a number", statement),
+            );
+        }
+
+        for &target in targets {
+            self.check_jump_target(statement, target);
+        }
+    }
+
+    fn check_jump_target(&mut self, statement: &str, line_number: u32) {
+        match self.program.lookup_line_or_next(line_number) {
+            Some((target, Statement::Rem { .. })) if target == line_number => {
+                self.hint(
+                    SemKind::SuspiciousJump,
+                    format!(
+                        "{} {} targets a REM line, execution continues after it",
+                        statement, line_number
+                    ),
+                );
+                return;
+            }
+            Some((target, Statement::Data { .. })) if target == line_number => {
+                self.hint(
+                    SemKind::SuspiciousJump,
+                    format!(
+                        "{} {} targets a DATA line, which does nothing when run",
+                        statement, line_number
+                    ),
+                );
+                return;
+            }
+            Some((target, _)) if target == line_number => return,
+            Some((target, _)) => {
+                self.warning(
+                    SemKind::UndefinedLine,
+                    format!(
+                        "{} to undefined line {} continues at line {}",
+                        statement, line_number, target
+                    ),
+                );
+                return;
+            }
+            None => {}
+        }
+
+        let message = format!("{} to undefined line {}", statement, line_number);
+        if self.allow_forward_refs {
+            self.warning(SemKind::UndefinedLine, message);
         } else {
-            Err(self.errors)
+            self.error(SemKind::UndefinedLine, message);
+        }
+    }
+
+    // A lone "" is almost always a message that was deleted by accident
+    fn check_empty_output(&mut self, statement: &str, content: &[&'a Expression]) {
+        if let [Expression::String(s)] = content {
+            if s.is_empty() {
+                self.warning(
+                    SemKind::NoEffect,
+                    format!("{} of an empty string has no effect", statement),
+                );
+            }
+        }
+    }
+
+    // Only the first allocation past the budget is reported, the ones after it
+    // would just repeat the same problem
+    fn allocate(&mut self, name: &'a str, kind: SymbolKind, bytes: usize, what: String) {
+        if !self.allocated.insert((name, kind)) {
+            return;
+        }
+
+        let was_within_budget = self.memory_used <= self.memory_budget;
+        // A huge DIM saturates, the next allocation must not overflow
+        self.memory_used = self.memory_used.saturating_add(bytes);
+
+        if was_within_budget && self.memory_used > self.memory_budget {
+            self.error(
+                SemKind::OutOfMemory,
+                format!(
+                    "{} needs {} bytes, bringing variables to {} bytes of the {} available",
+                    what, bytes, self.memory_used, self.memory_budget
+                ),
+            );
         }
     }
 
+    fn allocate_scalar(&mut self, name: &'a str) {
+        // Fixed variables A to Z and A$ to Z$ are always there
+        if name.trim_end_matches('$').len() == 1 {
+            return;
+        }
+
+        let bytes = match Ty::of_variable(name) {
+            Ty::Int | Ty::Real => NUMBER_SIZE,
+            Ty::String => DEFAULT_STRING_LENGTH,
+        };
+
+        self.allocate(
+            name,
+            SymbolKind::Scalar,
+            bytes,
+            format!("Variable {}", name),
+        );
+    }
+
+    // Lines are visited in order, so this ignores control flow: a use is only
+    // flagged if no DIM for the array appears on an earlier line.
+    fn use_lvalue(&mut self, lvalue: &'a LValue) {
+        if let LValue::Variable(name) = lvalue {
+            self.allocate_scalar(name);
+        }
+
+        if let (LValue::ArrayElement { variable, .. }, Some(line)) = (lvalue, self.line) {
+            if !self.dims.contains_key(variable.as_str()) {
+                self.undimmed_uses.entry(variable).or_insert(line);
+            }
+        }
+    }
+
+    // Every DATA item in the program, READ can reach any of them through
+    // RESTORE and loops
+    fn data_items(&self) -> Vec<&'a DataItem> {
+        let program = self.program;
+        let mut items = Vec::new();
+
+        for statement in program.values() {
+            let statements = match statement {
+                Statement::Seq { statements } => statements.as_slice(),
+                _ => std::slice::from_ref(statement),
+            };
+
+            for inner in statements {
+                if let Statement::Data { values } = inner {
+                    items.extend(values);
+                }
+            }
+        }
+
+        items
+    }
+
     fn get_ty(&self, name: &'a LValue) -> Ty {
         let name = match name {
             LValue::Variable(name) => name,
             LValue::ArrayElement { variable, .. } => variable,
         };
 
-        if name.ends_with("$") {
-            Ty::String
-        } else {
-            Ty::Int
-        }
+        Ty::of_variable(name)
     }
 }
 
 impl<'a> ExpressionVisitor<'a, Ty> for SemanticChecker<'a> {
     fn visit_variable(&mut self, name: &'a LValue) -> Ty {
+        self.use_lvalue(name);
         self.get_ty(name)
     }
 
@@ -68,19 +383,31 @@ impl<'a> ExpressionVisitor<'a, Ty> for SemanticChecker<'a> {
         Ty::Int
     }
 
+    fn visit_float_literal(&mut self, _: f64) -> Ty {
+        Ty::Real
+    }
+
     fn visit_unary_op(&mut self, op: UnaryOperator, operand: &'a Expression) -> Ty {
         let operand_ty = operand.accept(self);
         match op {
             UnaryOperator::Not => {
-                if operand_ty != Ty::Int {
-                    self.errors
-                        .push("NOT operand must be an integer".to_owned());
+                if !operand_ty.is_numeric() {
+                    self.error(
+                        SemKind::TypeMismatch,
+                        "NOT operand must be a number".to_owned(),
+                    );
                 }
             }
             UnaryOperator::Plus | UnaryOperator::Minus => {
+                if operand_ty == Ty::Real {
+                    return Ty::Real;
+                }
+
                 if operand_ty != Ty::Int {
-                    self.errors
-                        .push("Unary plus/minus operand must be an integer".to_owned());
+                    self.error(
+                        SemKind::TypeMismatch,
+                        "Unary plus/minus operand must be a number".to_owned(),
+                    );
                 }
             }
         }
@@ -97,23 +424,66 @@ impl<'a> ExpressionVisitor<'a, Ty> for SemanticChecker<'a> {
         let left_ty = left.accept(self);
         let right_ty = right.accept(self);
 
-        if left_ty != right_ty {
-            self.errors.push(format!(
-                "Type mismatch: left operand is {}, right operand is {}",
-                left_ty, right_ty
-            ));
+        // + on two strings is concatenation
+        if op == BinaryOperator::Add && left_ty == Ty::String && right_ty == Ty::String {
+            return Ty::String;
+        }
+
+        let zero_divisor = match right {
+            Expression::Number(value) => *value == 0,
+            Expression::Float(value) => *value == 0.0,
+            _ => false,
+        };
+        if matches!(op, BinaryOperator::Div | BinaryOperator::Mod) && zero_divisor {
+            self.warning(
+                SemKind::DivisionByZero,
+                format!("{} {} {} divides by zero", left, op, right),
+            );
         }
 
         match op {
+            BinaryOperator::Concat => {
+                for (operand, ty) in [(left, left_ty), (right, right_ty)] {
+                    if ty != Ty::String {
+                        self.error(
+                            SemKind::TypeMismatch,
+                            format!(
+                                "& joins strings but {} is a number, use STR$({}) to convert it",
+                                operand, operand
+                            ),
+                        );
+                    }
+                }
+
+                return Ty::String;
+            }
             BinaryOperator::Add
             | BinaryOperator::Sub
             | BinaryOperator::Mul
             | BinaryOperator::Div
+            | BinaryOperator::Mod
+            | BinaryOperator::Pow
             | BinaryOperator::And
             | BinaryOperator::Or => {
-                if left_ty != Ty::Int {
-                    self.errors
-                        .push("Arithmetic operands must be integers".to_owned());
+                for (operand, ty) in [(left, left_ty), (right, right_ty)] {
+                    if ty == Ty::String {
+                        self.error(
+                            SemKind::TypeMismatch,
+                            format!(
+                                "{} needs numbers but {} is a string, use VAL({}) to convert it",
+                                op, operand, operand
+                            ),
+                        );
+                    }
+                }
+
+                // AND, OR and MOD work on the integer parts
+                let is_integer = matches!(
+                    op,
+                    BinaryOperator::And | BinaryOperator::Or | BinaryOperator::Mod
+                );
+                if !is_integer && (left_ty == Ty::Real || right_ty == Ty::Real) {
+                    return Ty::Real;
                 }
             }
             BinaryOperator::Eq
@@ -122,8 +492,15 @@ impl<'a> ExpressionVisitor<'a, Ty> for SemanticChecker<'a> {
             | BinaryOperator::Le
             | BinaryOperator::Gt
             | BinaryOperator::Ge => {
-                // Itegers and string are comparable
+                // Integers and string are comparable
                 // in the case of strings, the comparison is lexicographical
+                // but the PC-1500 can't compare a string with a number
+                if left_ty.is_numeric() != right_ty.is_numeric() {
+                    self.error(
+                        SemKind::TypeMismatch,
+                        format!("Cannot compare {} with {}", left_ty, right_ty),
+                    );
+                }
             }
         }
 
@@ -133,34 +510,133 @@ impl<'a> ExpressionVisitor<'a, Ty> for SemanticChecker<'a> {
     fn visit_string_literal(&mut self, _: &'a str) -> Ty {
         Ty::String
     }
+
+    fn visit_varptr(&mut self, operand: &'a Expression) -> Ty {
+        if let Expression::LValue(_) = operand {
+            operand.accept(self);
+        } else {
+            self.error(
+                SemKind::TypeMismatch,
+                format!("VARPTR argument must be a variable, found {}", operand),
+            );
+        }
+
+        Ty::Int
+    }
+
+    fn visit_function(&mut self, name: &'a str, args: &'a [Expression]) -> Ty {
+        let arg_tys: Vec<Ty> = args.iter().map(|arg| arg.accept(self)).collect();
+
+        let (params, ret) = match signature(name) {
+            Some(signature) => signature,
+            None => {
+                self.error(SemKind::TypeMismatch, format!("Unknown function {}", name));
+                return Ty::Int;
+            }
+        };
+
+        if arg_tys.len() != params.len() {
+            self.error(
+                SemKind::ArgumentCount,
+                format!(
+                    "{} takes {} arguments, found {}",
+                    name,
+                    params.len(),
+                    arg_tys.len()
+                ),
+            );
+            return ret;
+        }
+
+        for (position, (&arg, &param)) in arg_tys.iter().zip(params).enumerate() {
+            if arg.is_numeric() != param.is_numeric() {
+                self.error(
+                    SemKind::TypeMismatch,
+                    format!(
+                        "Argument {} of {} must be {}, found {}",
+                        position + 1,
+                        name,
+                        param,
+                        arg
+                    ),
+                );
+            }
+        }
+
+        ret
+    }
 }
 
 impl<'a> StatementVisitor<'a> for SemanticChecker<'a> {
     fn visit_let(&mut self, variable: &'a LValue, expression: &'a Expression) {
+        if let Expression::LValue(source) = expression {
+            if source == variable {
+                self.warning(
+                    SemKind::NoEffect,
+                    format!("Assignment of {} to itself has no effect", variable),
+                );
+            }
+        }
+
+        self.use_lvalue(variable);
         let expr_ty = expression.accept(self);
         let expected_ty = self.get_ty(variable);
-        if expr_ty != expected_ty {
-            self.errors.push(format!(
-                "Type mismatch: variable {} is {}, expression is {}",
-                variable, expected_ty, expr_ty
-            ));
+        if expr_ty.is_numeric() != expected_ty.is_numeric() {
+            self.error(
+                SemKind::TypeMismatch,
+                format!(
+                    "Type mismatch: variable {} is {}, expression is {}",
+                    variable, expected_ty, expr_ty
+                ),
+            );
         }
     }
 
-    fn visit_print(&mut self, content: &'a [Expression]) {
-        for item in content {
+    fn visit_print(
+        &mut self,
+        content: &'a [(Expression, Separator)],
+        _: bool,
+        _: bool,
+        device: OutputDevice,
+    ) {
+        let items: Vec<&Expression> = content.iter().map(|(item, _)| item).collect();
+
+        // LPRINT "" feeds a blank line on the printer, which is useful
+        if device == OutputDevice::Display {
+            self.check_empty_output("PRINT", &items);
+        }
+
+        for item in items {
             item.accept(self);
         }
     }
 
     fn visit_pause(&mut self, content: &'a [Expression]) {
+        let items: Vec<&Expression> = content.iter().collect();
+        self.check_empty_output("PAUSE", &items);
+
         for item in content {
             item.accept(self);
         }
     }
 
-    fn visit_input(&mut self, _: Option<&'a Expression>, _: &'a LValue) {
+    fn visit_input(
+        &mut self,
+        device: Option<&'a Expression>,
+        _: Option<&'a Expression>,
+        variable: &'a LValue,
+    ) {
+        self.use_lvalue(variable);
+
         // TODO: check prompt is string? Are integer prompts allowed?
+        if let Some(device) = device {
+            if !device.accept(self).is_numeric() {
+                self.error(
+                    SemKind::TypeMismatch,
+                    "INPUT device must be a number".to_owned(),
+                );
+            }
+        }
     }
 
     fn visit_wait(&mut self, _: Option<&'a Expression>) {
@@ -168,11 +644,7 @@ impl<'a> StatementVisitor<'a> for SemanticChecker<'a> {
     }
 
     fn visit_goto(&mut self, line_number: u32) {
-        let to_node = self.program.lookup_line(line_number);
-        if to_node.is_none() {
-            self.errors
-                .push(format!("GOTO to undefined line {}", line_number));
-        }
+        self.check_jump_target("GOTO", line_number);
     }
 
     fn visit_for(
@@ -182,68 +654,103 @@ impl<'a> StatementVisitor<'a> for SemanticChecker<'a> {
         to: &'a Expression,
         step: Option<&'a Expression>,
     ) {
-        let var_ty = if variable.ends_with("$") {
-            Ty::String
-        } else {
-            Ty::Int
-        };
-
-        if var_ty != Ty::Int {
-            self.errors
-                .push("Loop variable must be an integer".to_owned());
+        if !Ty::of_variable(variable).is_numeric() {
+            self.error(
+                SemKind::TypeMismatch,
+                "Loop variable must be a number".to_owned(),
+            );
         }
 
         let from_ty = from.accept(self);
         let to_ty = to.accept(self);
 
-        if from_ty != Ty::Int || to_ty != Ty::Int {
-            self.errors.push("Loop bounds must be integers".to_owned());
+        if !from_ty.is_numeric() || !to_ty.is_numeric() {
+            self.error(
+                SemKind::TypeMismatch,
+                "Loop bounds must be numbers".to_owned(),
+            );
         }
 
         if let Some(step) = step {
             let step_ty = step.accept(self);
-            if step_ty != Ty::Int {
-                self.errors.push("Loop step must be an integer".to_owned());
+            if !step_ty.is_numeric() {
+                self.error(
+                    SemKind::TypeMismatch,
+                    "Loop step must be a number".to_owned(),
+                );
             }
         }
 
-        self.for_stack.push(variable);
+        self.allocate_scalar(variable);
+        self.for_stack
+            .push((variable, self.line.unwrap_or_default()));
     }
 
-    fn visit_next(&mut self, variable: &'a str) {
-        let var_ty = if variable.ends_with("$") {
-            Ty::String
-        } else {
-            Ty::Int
-        };
-
-        if var_ty != Ty::Int {
-            self.errors
-                .push("Loop variable must be an integer".to_owned());
+    fn visit_next(&mut self, variable: Option<&'a str>) {
+        if let Some(variable) = variable {
+            if !Ty::of_variable(variable).is_numeric() {
+                self.error(
+                    SemKind::TypeMismatch,
+                    "Loop variable must be a number".to_owned(),
+                );
+            }
         }
 
-        if let Some(last) = self.for_stack.pop() {
-            if last != variable {
-                self.errors.push(
-                    "NEXT variable: ".to_owned()
-                        + variable
-                        + " does not match FOR variable: "
-                        + last,
+        // A bare NEXT closes the innermost loop. NEXT for an outer loop is
+        // reported once as crossing, the inner loop stays open for its own NEXT.
+        let open = variable.and_then(|variable| {
+            self.for_stack
+                .iter()
+                .rposition(|&(name, _)| name == variable)
+        });
+
+        match (self.for_stack.last().copied(), variable, open) {
+            (Some((last, _)), Some(variable), Some(open)) if last != variable => {
+                let (_, line) = self.for_stack.remove(open);
+                self.error(
+                    SemKind::LoopMismatch,
+                    format!(
+                        "NEXT {} closes the loop from line {} while the loop over {} is still open, the loops cross",
+                        variable, line, last
+                    ),
+                );
+            }
+            (Some((last, _)), Some(variable), None) if last != variable => {
+                self.for_stack.pop();
+                self.error(
+                    SemKind::LoopMismatch,
+                    format!(
+                        "NEXT variable: {} does not match FOR variable: {}",
+                        variable, last
+                    ),
+                );
+            }
+            (Some(_), _, _) => {
+                self.for_stack.pop();
+            }
+            (None, _, _) => {
+                self.error(
+                    SemKind::LoopMismatch,
+                    "NEXT without matching FOR".to_owned(),
                 );
             }
-        } else {
-            self.errors.push("NEXT without matching FOR".to_owned());
         }
     }
 
     fn visit_end(&mut self) {}
 
+    fn visit_stop(&mut self) {}
+
     fn visit_gosub(&mut self, line_number: u32) {
-        let to_node = self.program.lookup_line(line_number);
-        if to_node.is_none() {
-            self.errors
-                .push(format!("GOSUB to undefined line {}", line_number));
-        }
+        self.check_jump_target("GOSUB", line_number);
+    }
+
+    fn visit_on_goto(&mut self, selector: &'a Expression, targets: &'a [u32]) {
+        self.check_on(selector, "ON ... GOTO", targets);
+    }
+
+    fn visit_on_gosub(&mut self, selector: &'a Expression, targets: &'a [u32]) {
+        self.check_on(selector, "ON ... GOSUB", targets);
     }
 
     fn visit_return(&mut self) {}
@@ -254,9 +761,24 @@ impl<'a> StatementVisitor<'a> for SemanticChecker<'a> {
         then: &'a Statement,
         else_: Option<&'a Statement>,
     ) {
+        // IF A is legal and tests A <> 0, but is often a comparison missing its
+        // right-hand side
+        if let Expression::LValue(variable) = condition {
+            self.hint(
+                SemKind::SuspiciousCondition,
+                format!(
+                    "Condition {} is true whenever {} is not 0, was a comparison intended?",
+                    variable, variable
+                ),
+            );
+        }
+
         let condition_ty = condition.accept(self);
-        if condition_ty != Ty::Int {
-            self.errors.push("Condition must be an integer".to_owned());
+        if !condition_ty.is_numeric() {
+            self.error(
+                SemKind::TypeMismatch,
+                "Condition must be a number".to_owned(),
+            );
         }
 
         then.accept(self);
@@ -265,7 +787,34 @@ impl<'a> StatementVisitor<'a> for SemanticChecker<'a> {
         }
     }
 
+    // A jump or END leaves the line, so the rest of it never runs. Comments
+    // after it are fine, and STOP can be continued past.
     fn visit_seq(&mut self, statements: &'a [Statement]) {
+        let leaves = statements.iter().position(|statement| {
+            matches!(
+                statement,
+                Statement::Goto { .. } | Statement::End | Statement::Return
+            )
+        });
+
+        if let Some(leaves) = leaves {
+            let (keyword, rest) = match statements.split_at(leaves + 1) {
+                ([.., Statement::Goto { .. }], rest) => ("GOTO", rest),
+                ([.., Statement::End], rest) => ("END", rest),
+                (_, rest) => ("RETURN", rest),
+            };
+
+            if rest
+                .iter()
+                .any(|statement| !matches!(statement, Statement::Rem { .. }))
+            {
+                self.warning(
+                    SemKind::Unreachable,
+                    format!("Code after {} on the same line is never run", keyword),
+                );
+            }
+        }
+
         for statement in statements {
             statement.accept(self);
         }
@@ -273,18 +822,45 @@ impl<'a> StatementVisitor<'a> for SemanticChecker<'a> {
 
     fn visit_rem(&mut self, _: &'a str) {}
 
-    fn visit_read(&mut self, _variables: &'a [LValue]) {
-        // TODO: is it possible to check types of read variables? Probably not
+    // Which item a READ gets depends on control flow, so only a target no
+    // item can be read into is an error. A string variable takes any item,
+    // numbers are read as they are spelled, but a number variable cannot
+    // read a string.
+    fn visit_read(&mut self, variables: &'a [LValue]) {
+        let items = self.data_items();
+        let has_number = items
+            .iter()
+            .any(|item| matches!(item, DataItem::Number { .. } | DataItem::Real { .. }));
+
+        for variable in variables {
+            self.use_lvalue(variable);
+
+            if !self.get_ty(variable).is_numeric() {
+                continue;
+            }
+
+            if !items.is_empty() && !has_number {
+                self.error(
+                    SemKind::TypeMismatch,
+                    format!(
+                        "READ {} needs a number but every DATA item is a string",
+                        variable
+                    ),
+                );
+            }
+        }
     }
 
-    fn visit_data(&mut self, _values: &'a [super::node::DataItem]) {}
+    fn visit_data(&mut self, _values: &'a [DataItem]) {}
 
     fn visit_restore(&mut self, line_number: Option<u32>) {
         if let Some(line_number) = line_number {
             let to_node = self.program.lookup_line(line_number);
             if to_node.is_none() {
-                self.errors
-                    .push(format!("RESTORE undefined line {}", line_number));
+                self.error(
+                    SemKind::UndefinedLine,
+                    format!("RESTORE undefined line {}", line_number),
+                );
             }
 
             // Check that the line number is a DATA statement
@@ -292,54 +868,1267 @@ impl<'a> StatementVisitor<'a> for SemanticChecker<'a> {
                 if let Statement::Data { .. } = to_node {
                     // Ok
                 } else {
-                    self.errors.push(format!(
-                        "RESTORE to non-DATA statement at line {}",
-                        line_number
-                    ));
+                    self.error(
+                        SemKind::InvalidRestore,
+                        format!("RESTORE to non-DATA statement at line {}", line_number),
+                    );
                 }
             }
         }
     }
 
-    fn visit_poke(&mut self, _address: u32, _values: &'a [u8]) {
-        // TODO: maybe check adress is in wirtable memory?
+    fn visit_poke(&mut self, address: u32, values: &'a [u8]) {
         // Check that the literals fit in a byte is done in parsing
+        // The address is always a literal, so the whole written range is known here
+        let last = address.saturating_add(values.len().saturating_sub(1) as u32);
+
+        if last > MAX_ADDRESS {
+            self.error(
+                SemKind::InvalidAddress,
+                format!("POKE to &{:X} is outside the address space", address),
+            );
+            return;
+        }
+
+        for (start, end, region) in ROM_REGIONS {
+            if address <= end && last >= start {
+                self.error(
+                    SemKind::InvalidAddress,
+                    format!(
+                        "POKE to &{:04X} writes into {} (&{:04X}-&{:04X})",
+                        address.max(start),
+                        region,
+                        start,
+                        end
+                    ),
+                );
+            }
+        }
     }
 
     fn visit_call(&mut self, _address: u32) {
         // TODO: maybe check that there is a matching POKE to the address? Although this is not a strict requirement
     }
 
-    fn visit_dim(&mut self, variable: &'a str, size: u32, length: Option<u32>) {
-        let var_ty = if variable.ends_with("$") {
-            Ty::String
+    fn visit_dim(&mut self, variable: &'a str, sizes: &'a [u32], length: Option<u32>) {
+        if let Some(line) = self.undimmed_uses.remove(variable) {
+            self.diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                kind: SemKind::ArrayBeforeDim,
+                message: format!(
+                    "Array {} is used before its DIM on line {}",
+                    variable,
+                    self.line.unwrap_or_default()
+                ),
+                line: Some(line),
+            });
+        }
+
+        if let Some(&first) = self.dims.get(variable) {
+            self.error(
+                SemKind::Redeclaration,
+                format!(
+                    "Array {} is declared again, it was first declared on line {}",
+                    variable, first
+                ),
+            );
         } else {
-            Ty::Int
-        };
+            self.dims.insert(variable, self.line.unwrap_or_default());
+        }
+
+        let var_ty = Ty::of_variable(variable);
 
-        if size > 255 {
-            self.errors
-                .push("Array size must be between 0 and 255".to_owned());
+        for &size in sizes {
+            if size > 255 {
+                self.error(
+                    SemKind::InvalidDim,
+                    "Array size must be between 0 and 255".to_owned(),
+                );
+            }
         }
 
-        if var_ty == Ty::Int && length.is_some() {
-            self.errors
-                .push("INT variables cannot have length".to_owned());
+        if var_ty.is_numeric() && length.is_some() {
+            self.error(
+                SemKind::InvalidDim,
+                "Number variables cannot have length".to_owned(),
+            );
         }
 
         if let Some(length) = length {
             if !(1..=80).contains(&length) {
-                self.errors
-                    .push("String length must be between 1 and 80".to_owned());
+                self.error(
+                    SemKind::InvalidDim,
+                    "String length must be between 1 and 80".to_owned(),
+                );
+            }
+        }
+
+        let element_size = match var_ty {
+            Ty::Int | Ty::Real => NUMBER_SIZE,
+            Ty::String => length.map_or(DEFAULT_STRING_LENGTH, |length| length as usize),
+        };
+        // Each dimension runs from 0 to its size
+        let elements = sizes.iter().fold(1, |count: usize, &size| {
+            count.saturating_mul(size as usize + 1)
+        });
+        let sizes: Vec<String> = sizes.iter().map(u32::to_string).collect();
+        self.allocate(
+            variable,
+            SymbolKind::Array,
+            ARRAY_HEADER_SIZE.saturating_add(elements.saturating_mul(element_size)),
+            format!("DIM {}({})", variable, sizes.join(",")),
+        );
+    }
+
+    fn visit_clear(&mut self, size: Option<&'a Expression>) {
+        if let Some(size) = size {
+            if !size.accept(self).is_numeric() {
+                self.error(
+                    SemKind::TypeMismatch,
+                    "CLEAR size must be a number".to_owned(),
+                );
             }
         }
     }
+
+    fn visit_chain(&mut self, name: &'a Expression) {
+        if name.accept(self) != Ty::String {
+            self.error(
+                SemKind::TypeMismatch,
+                "CHAIN program name must be a string".to_owned(),
+            );
+        }
+    }
 }
 
 impl<'a> ProgramVisitor<'a> for SemanticChecker<'a> {
     fn visit_program(&mut self, program: &'a Program) {
-        for statement in program.values() {
+        for (line_number, statement) in program.iter() {
+            self.line = Some(*line_number as usize);
             statement.accept(self);
         }
+
+        for (variable, line) in mem::take(&mut self.for_stack) {
+            self.line = Some(line);
+            self.error(
+                SemKind::LoopMismatch,
+                format!("FOR without NEXT for variable {}", variable),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(lines: Vec<(u32, Statement)>) -> Vec<String> {
+        let mut program = Program::new();
+        for (line_number, statement) in lines {
+            program.add_line(line_number, statement);
+        }
+
+        SemanticChecker::new(&program)
+            .check()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn literal_zero_divisor_warns() {
+        let divide = |op, divisor| Statement::Let {
+            variable: LValue::Variable("A".to_owned()),
+            expression: Expression::Binary {
+                left: Box::new(Expression::Number(7)),
+                op,
+                right: Box::new(divisor),
+            },
+        };
+
+        let res = check(vec![
+            (10, divide(BinaryOperator::Div, Expression::Number(0))),
+            (20, divide(BinaryOperator::Mod, Expression::Number(0))),
+            (30, divide(BinaryOperator::Mod, Expression::Number(3))),
+            (40, divide(BinaryOperator::Div, Expression::Float(0.0))),
+            (50, divide(BinaryOperator::Div, Expression::Float(0.5))),
+        ]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Warning at line 10: 7 / 0 divides by zero".to_owned(),
+                "Warning at line 20: 7 MOD 0 divides by zero".to_owned(),
+                "Warning at line 40: 7 / 0E0 divides by zero".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn print_empty_string_warns() {
+        let res = check(vec![(
+            10,
+            Statement::Print {
+                content: vec![(Expression::String(String::new()), Separator::None)],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Warning at line 10: PRINT of an empty string has no effect".to_owned()]
+        );
+    }
+
+    #[test]
+    fn pause_empty_string_warns() {
+        let res = check(vec![(
+            10,
+            Statement::Pause {
+                content: vec![Expression::String(String::new())],
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Warning at line 10: PAUSE of an empty string has no effect".to_owned()]
+        );
+    }
+
+    #[test]
+    fn poke_rom_errors() {
+        let res = check(vec![(
+            10,
+            Statement::Poke {
+                address: 0xC000,
+                values: vec![1],
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: POKE to &C000 writes into system ROM (&C000-&FFFF)".to_owned()]
+        );
+    }
+
+    #[test]
+    fn poke_ram_ok() {
+        let res = check(vec![(
+            10,
+            Statement::Poke {
+                address: 0x4000,
+                values: vec![1, 2, 3],
+            },
+        )]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn goto_undefined_line_errors() {
+        let res = check(vec![(10, Statement::Goto { line_number: 20 })]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: GOTO to undefined line 20".to_owned()]
+        );
+    }
+
+    #[test]
+    fn on_goto_undefined_target_errors() {
+        let res = check(vec![
+            (
+                10,
+                Statement::OnGoto {
+                    selector: Expression::LValue(LValue::Variable("X".to_owned())),
+                    targets: vec![10, 30],
+                },
+            ),
+            (20, Statement::End),
+        ]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 10: ON ... GOTO to undefined line 30".to_owned(),
+                "Warning at line 10: X is never assigned, it is always 0".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn unassigned_variable_warns() {
+        let print_a = || Statement::Print {
+            content: vec![(
+                Expression::LValue(LValue::Variable("A".to_owned())),
+                Separator::None,
+            )],
+            continue_line: false,
+            suppress_newline: false,
+            device: OutputDevice::Display,
+        };
+
+        assert_eq!(
+            check(vec![(10, print_a())]),
+            vec!["Warning at line 10: A is never assigned, it is always 0".to_owned()]
+        );
+
+        let assigned = check(vec![(
+            10,
+            Statement::Seq {
+                statements: vec![
+                    Statement::Let {
+                        variable: LValue::Variable("A".to_owned()),
+                        expression: Expression::Number(1),
+                    },
+                    print_a(),
+                ],
+            },
+        )]);
+        assert!(assigned.is_empty());
+
+        // A$ is a different variable from A
+        let string = check(vec![(10, input_from(None)), (20, print_a())]);
+        assert_eq!(
+            string,
+            vec!["Warning at line 20: A is never assigned, it is always 0".to_owned()]
+        );
+    }
+
+    #[test]
+    fn code_after_goto_warns() {
+        let print = || Statement::Print {
+            content: vec![(Expression::String("never".to_owned()), Separator::None)],
+            continue_line: false,
+            suppress_newline: false,
+            device: OutputDevice::Display,
+        };
+
+        let res = check(vec![
+            (
+                10,
+                Statement::Seq {
+                    statements: vec![
+                        Statement::Goto { line_number: 20 },
+                        Statement::Rem {
+                            content: String::new(),
+                        },
+                        print(),
+                    ],
+                },
+            ),
+            (
+                20,
+                Statement::Seq {
+                    statements: vec![
+                        Statement::If {
+                            condition: Expression::LValue(LValue::Variable("A".to_owned())),
+                            then: Box::new(Statement::Goto { line_number: 10 }),
+                            else_: None,
+                        },
+                        print(),
+                    ],
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Warning at line 10: Code after GOTO on the same line is never run".to_owned(),
+                "Hint at line 20: Condition A is true whenever A is not 0, was a comparison intended?"
+                    .to_owned(),
+                "Warning at line 20: A is never assigned, it is always 0".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn goto_undefined_line_warns_with_forward_refs() {
+        let mut program = Program::new();
+        program.add_line(10, Statement::GoSub { line_number: 20 });
+
+        let res = SemanticChecker::new(&program)
+            .allow_forward_refs(true)
+            .check();
+
+        assert_eq!(
+            res,
+            vec![Diagnostic {
+                severity: Severity::Warning,
+                kind: SemKind::UndefinedLine,
+                message: "GOSUB to undefined line 20".to_owned(),
+                line: Some(10),
+            }]
+        );
+    }
+
+    #[test]
+    fn bare_next_closes_loop() {
+        let res = check(vec![
+            (
+                10,
+                Statement::For {
+                    variable: "I".to_owned(),
+                    from: Expression::Number(1),
+                    to: Expression::Number(10),
+                    step: None,
+                },
+            ),
+            (20, Statement::Next { variable: None }),
+        ]);
+
+        assert!(res.is_empty());
+    }
+
+    fn for_(variable: &str) -> Statement {
+        Statement::For {
+            variable: variable.to_owned(),
+            from: Expression::Number(1),
+            to: Expression::Number(10),
+            step: None,
+        }
+    }
+
+    fn next(variable: &str) -> Statement {
+        Statement::Next {
+            variable: Some(variable.to_owned()),
+        }
+    }
+
+    #[test]
+    fn for_without_next_errors() {
+        let res = check(vec![
+            (10, for_("I")),
+            (20, for_("J")),
+            (30, next("J")),
+            (40, Statement::End),
+        ]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: FOR without NEXT for variable I".to_owned()]
+        );
+    }
+
+    #[test]
+    fn crossed_loops_error() {
+        let res = check(vec![
+            (10, for_("I")),
+            (20, for_("J")),
+            (30, next("I")),
+            (40, next("J")),
+        ]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 30: NEXT I closes the loop from line 10 while the loop over J is still open, the loops cross"
+                    .to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn bare_next_without_for_errors() {
+        let res = check(vec![(10, Statement::Next { variable: None })]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: NEXT without matching FOR".to_owned()]
+        );
+    }
+
+    fn compare_string(right: Expression) -> Statement {
+        Statement::If {
+            condition: Expression::Binary {
+                left: Box::new(Expression::LValue(LValue::Variable("A$".to_owned()))),
+                op: BinaryOperator::Lt,
+                right: Box::new(right),
+            },
+            then: Box::new(Statement::End),
+            else_: None,
+        }
+    }
+
+    #[test]
+    fn compare_string_with_number_errors() {
+        let res = check(vec![(10, compare_string(Expression::Number(5)))]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 10: Cannot compare STR with INT".to_owned(),
+                "Warning at line 10: A$ is never assigned, it is always an empty string".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn compare_strings_ok() {
+        let res = check(vec![
+            (10, input_from(None)),
+            (20, compare_string(Expression::String("m".to_owned()))),
+        ]);
+
+        assert!(res.is_empty());
+    }
+
+    fn input_from(device: Option<Expression>) -> Statement {
+        Statement::Input {
+            device,
+            prompt: None,
+            variable: LValue::Variable("A$".to_owned()),
+        }
+    }
+
+    #[test]
+    fn input_keyboard_ok() {
+        let res = check(vec![(10, input_from(None))]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn input_string_device_errors() {
+        let res = check(vec![(
+            10,
+            input_from(Some(Expression::String("2".to_owned()))),
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: INPUT device must be a number".to_owned()]
+        );
+    }
+
+    #[test]
+    fn self_assignment_warns() {
+        let res = check(vec![(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: Expression::LValue(LValue::Variable("A".to_owned())),
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Warning at line 10: Assignment of A to itself has no effect".to_owned()]
+        );
+    }
+
+    #[test]
+    fn increment_no_warning() {
+        let res = check(vec![(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: Expression::Binary {
+                    left: Box::new(Expression::LValue(LValue::Variable("A".to_owned()))),
+                    op: BinaryOperator::Add,
+                    right: Box::new(Expression::Number(1)),
+                },
+            },
+        )]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn clear_string_size_errors() {
+        let res = check(vec![(
+            10,
+            Statement::Clear {
+                size: Some(Expression::String("100".to_owned())),
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: CLEAR size must be a number".to_owned()]
+        );
+    }
+
+    fn string_op(op: BinaryOperator) -> Expression {
+        Expression::Binary {
+            left: Box::new(Expression::LValue(LValue::Variable("A$".to_owned()))),
+            op,
+            right: Box::new(Expression::LValue(LValue::Variable("B$".to_owned()))),
+        }
+    }
+
+    #[test]
+    fn string_concatenation_is_string() {
+        let res = check(vec![
+            (5, input_from(None)),
+            (
+                6,
+                Statement::Let {
+                    variable: LValue::Variable("B$".to_owned()),
+                    expression: Expression::String("b".to_owned()),
+                },
+            ),
+            (
+                10,
+                Statement::Print {
+                    content: vec![(string_op(BinaryOperator::Add), Separator::None)],
+                    continue_line: false,
+                    suppress_newline: false,
+                    device: OutputDevice::Display,
+                },
+            ),
+            (
+                20,
+                Statement::Let {
+                    variable: LValue::Variable("C$".to_owned()),
+                    expression: string_op(BinaryOperator::Add),
+                },
+            ),
+        ]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn string_subtraction_errors() {
+        let res = check(vec![(
+            10,
+            Statement::Print {
+                content: vec![(string_op(BinaryOperator::Sub), Separator::None)],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 10: - needs numbers but A$ is a string, use VAL(A$) to convert it"
+                    .to_owned(),
+                "Error at line 10: - needs numbers but B$ is a string, use VAL(B$) to convert it"
+                    .to_owned(),
+                "Warning at line 10: A$ is never assigned, it is always an empty string".to_owned(),
+                "Warning at line 10: B$ is never assigned, it is always an empty string".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_mismatch_is_error() {
+        let mut program = Program::new();
+        program.add_line(
+            30,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: Expression::String("hi".to_owned()),
+            },
+        );
+
+        let res = SemanticChecker::new(&program).check();
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].severity, Severity::Error);
+        assert_eq!(res[0].kind, SemKind::TypeMismatch);
+        assert_eq!(res[0].line, Some(30));
+    }
+
+    #[test]
+    fn print_string_no_warning() {
+        let res = check(vec![(
+            10,
+            Statement::Print {
+                content: vec![(Expression::String("x".to_owned()), Separator::None)],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        )]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn varptr_of_expression_errors() {
+        let res = check(vec![(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: Expression::Varptr(Box::new(Expression::Binary {
+                    left: Box::new(Expression::Number(1)),
+                    op: BinaryOperator::Add,
+                    right: Box::new(Expression::Number(2)),
+                })),
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: VARPTR argument must be a variable, found 1 + 2".to_owned()]
+        );
+    }
+
+    #[test]
+    fn array_used_before_dim_errors() {
+        let res = check(vec![
+            (
+                10,
+                Statement::Let {
+                    variable: LValue::ArrayElement {
+                        variable: "A".to_owned(),
+                        indices: vec![Expression::Number(1)],
+                    },
+                    expression: Expression::Number(5),
+                },
+            ),
+            (
+                20,
+                Statement::Dim {
+                    variable: "A".to_owned(),
+                    sizes: vec![3],
+                    length: None,
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: Array A is used before its DIM on line 20".to_owned()]
+        );
+    }
+
+    #[test]
+    fn bare_variable_condition_hints() {
+        let res = check(vec![(
+            10,
+            Statement::If {
+                condition: Expression::LValue(LValue::Variable("A".to_owned())),
+                then: Box::new(Statement::End),
+                else_: None,
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Hint at line 10: Condition A is true whenever A is not 0, was a comparison intended?"
+                    .to_owned(),
+                "Warning at line 10: A is never assigned, it is always 0".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn goto_missing_line_resolves_to_next() {
+        let res = check(vec![
+            (10, Statement::Goto { line_number: 15 }),
+            (20, Statement::End),
+        ]);
+
+        assert_eq!(
+            res,
+            vec!["Warning at line 10: GOTO to undefined line 15 continues at line 20".to_owned()]
+        );
+    }
+
+    #[test]
+    fn arrays_exceed_memory_budget() {
+        let dim = |variable: &str| Statement::Dim {
+            variable: variable.to_owned(),
+            sizes: vec![100],
+            length: None,
+        };
+
+        let res = check(vec![(10, dim("A")), (20, dim("B")), (30, dim("C"))]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 30: DIM C(100) needs 815 bytes, bringing variables to 2445 bytes of the 1850 available"
+                    .to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn fixed_variables_are_free() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: Expression::Number(1),
+            },
+        );
+        program.add_line(
+            20,
+            Statement::Let {
+                variable: LValue::Variable("AB".to_owned()),
+                expression: Expression::Number(1),
+            },
+        );
+
+        let res = SemanticChecker::new(&program).memory_budget(4).check();
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].kind, SemKind::OutOfMemory);
+        assert_eq!(res[0].line, Some(20));
+    }
+
+    #[test]
+    fn string_times_number_suggests_val() {
+        let res = check(vec![(
+            10,
+            Statement::Print {
+                content: vec![(
+                    Expression::Binary {
+                        left: Box::new(Expression::LValue(LValue::Variable("A$".to_owned()))),
+                        op: BinaryOperator::Mul,
+                        right: Box::new(Expression::Number(2)),
+                    },
+                    Separator::None,
+                )],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 10: * needs numbers but A$ is a string, use VAL(A$) to convert it"
+                    .to_owned(),
+                "Warning at line 10: A$ is never assigned, it is always an empty string".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn chain_name_must_be_string() {
+        let res = check(vec![(
+            10,
+            Statement::Chain {
+                name: Expression::Number(2),
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: CHAIN program name must be a string".to_owned()]
+        );
+    }
+
+    #[test]
+    fn goto_rem_line_hints() {
+        let res = check(vec![
+            (10, Statement::Goto { line_number: 20 }),
+            (
+                20,
+                Statement::Rem {
+                    content: "loop".to_owned(),
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Hint at line 10: GOTO 20 targets a REM line, execution continues after it"
+                    .to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn goto_normal_line_is_fine() {
+        let res = check(vec![
+            (10, Statement::Goto { line_number: 20 }),
+            (20, Statement::End),
+        ]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn every_dimension_is_checked() {
+        let dim = |sizes| Statement::Dim {
+            variable: "A".to_owned(),
+            sizes,
+            length: None,
+        };
+
+        assert!(check(vec![(10, dim(vec![3, 4]))]).is_empty());
+        assert_eq!(
+            check(vec![(10, dim(vec![0, 256]))]).first(),
+            Some(&"Error at line 10: Array size must be between 0 and 255".to_owned())
+        );
+        assert_eq!(
+            check(vec![(10, dim(vec![20, 20]))]),
+            vec![
+                "Error at line 10: DIM A(20,20) needs 3535 bytes, bringing variables to 3535 bytes of the 1850 available"
+                    .to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn huge_dims_do_not_overflow() {
+        let dim = |variable: &str| Statement::Dim {
+            variable: variable.to_owned(),
+            sizes: vec![255; 8],
+            length: None,
+        };
+
+        let res = check(vec![(10, dim("A")), (20, dim("B"))]);
+
+        assert_eq!(res.len(), 1);
+        assert!(
+            res[0].starts_with("Error at line 10: DIM A(255,255,255,255,255,255,255,255) needs")
+        );
+    }
+
+    #[test]
+    fn double_dim_errors() {
+        let dim = |variable: &str, size| Statement::Dim {
+            variable: variable.to_owned(),
+            sizes: vec![size],
+            length: None,
+        };
+
+        let res = check(vec![(10, dim("A", 5)), (20, dim("A", 10))]);
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 20: Array A is declared again, it was first declared on line 10"
+                    .to_owned()
+            ]
+        );
+
+        let distinct = check(vec![(10, dim("A", 5)), (20, dim("B", 10))]);
+        assert!(distinct.is_empty());
+    }
+
+    #[test]
+    fn unconditional_recursion_errors() {
+        let res = check(vec![(100, Statement::GoSub { line_number: 100 })]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 100: Subroutine at line 100 always calls itself and will overflow the GOSUB stack"
+                    .to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn conditional_recursion_warns() {
+        let res = check(vec![
+            (
+                100,
+                Statement::If {
+                    condition: Expression::Binary {
+                        left: Box::new(Expression::LValue(LValue::Variable("A".to_owned()))),
+                        op: BinaryOperator::Gt,
+                        right: Box::new(Expression::Number(0)),
+                    },
+                    then: Box::new(Statement::GoSub { line_number: 100 }),
+                    else_: None,
+                },
+            ),
+            (110, Statement::Return),
+        ]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Warning at line 100: Subroutine at line 100 may call itself, deep recursion overflows the GOSUB stack"
+                    .to_owned(),
+                "Warning at line 100: A is never assigned, it is always 0".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn recursion_with_base_case_warns() {
+        let n = || Expression::LValue(LValue::Variable("N".to_owned()));
+        let res = check(vec![
+            (
+                100,
+                Statement::If {
+                    condition: Expression::Binary {
+                        left: Box::new(n()),
+                        op: BinaryOperator::Eq,
+                        right: Box::new(Expression::Number(0)),
+                    },
+                    then: Box::new(Statement::Return),
+                    else_: None,
+                },
+            ),
+            (
+                110,
+                Statement::Seq {
+                    statements: vec![
+                        Statement::Let {
+                            variable: LValue::Variable("N".to_owned()),
+                            expression: Expression::Binary {
+                                left: Box::new(n()),
+                                op: BinaryOperator::Sub,
+                                right: Box::new(Expression::Number(1)),
+                            },
+                        },
+                        Statement::GoSub { line_number: 100 },
+                    ],
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Warning at line 100: Subroutine at line 100 may call itself, deep recursion overflows the GOSUB stack"
+                    .to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn code_after_stop_is_reachable() {
+        let recursive = |first| {
+            check(vec![(
+                100,
+                Statement::Seq {
+                    statements: vec![first, Statement::GoSub { line_number: 100 }],
+                },
+            )])
+        };
+
+        assert_eq!(
+            recursive(Statement::End),
+            vec!["Warning at line 100: Code after END on the same line is never run".to_owned()]
+        );
+        assert_eq!(
+            recursive(Statement::Stop),
+            vec![
+                "Error at line 100: Subroutine at line 100 always calls itself and will overflow the GOSUB stack"
+                    .to_owned()
+            ]
+        );
+    }
+
+    fn mid(args: Vec<Expression>) -> Statement {
+        Statement::Let {
+            variable: LValue::Variable("B$".to_owned()),
+            expression: Expression::Call {
+                name: "MID$".to_owned(),
+                args,
+            },
+        }
+    }
+
+    #[test]
+    fn mid_argument_count_errors() {
+        let res = check(vec![(
+            10,
+            mid(vec![
+                Expression::String("abc".to_owned()),
+                Expression::Number(1),
+            ]),
+        )]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: MID$ takes 3 arguments, found 2".to_owned()]
+        );
+    }
+
+    #[test]
+    fn mid_argument_type_errors() {
+        let res = check(vec![(
+            10,
+            mid(vec![
+                Expression::Number(1),
+                Expression::Number(1),
+                Expression::String("2".to_owned()),
+            ]),
+        )]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 10: Argument 1 of MID$ must be STR, found INT".to_owned(),
+                "Error at line 10: Argument 3 of MID$ must be INT, found STR".to_owned(),
+            ]
+        );
+    }
+
+    #[test]
+    fn builtin_return_types() {
+        let len = Expression::Call {
+            name: "LEN".to_owned(),
+            args: vec![Expression::String("abc".to_owned())],
+        };
+        let res = check(vec![(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A$".to_owned()),
+                expression: len,
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 10: Type mismatch: variable A$ is STR, expression is INT".to_owned()
+            ]
+        );
+    }
+
+    // The conversions suggested by the type mismatch messages exist
+    #[test]
+    fn val_and_str_convert() {
+        let val = Expression::Call {
+            name: "VAL".to_owned(),
+            args: vec![Expression::String("12".to_owned())],
+        };
+        let str = Expression::Call {
+            name: "STR$".to_owned(),
+            args: vec![val],
+        };
+        let res = check(vec![(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: str,
+            },
+        )]);
+
+        assert_eq!(
+            res,
+            vec![
+                "Error at line 10: Type mismatch: variable A is REAL, expression is STR".to_owned()
+            ]
+        );
+    }
+
+    fn data(values: Vec<DataItem>) -> Statement {
+        Statement::Data { values }
+    }
+
+    fn read(name: &str) -> Statement {
+        Statement::Read {
+            variables: vec![LValue::Variable(name.to_owned())],
+        }
+    }
+
+    fn number_item(value: i32) -> DataItem {
+        DataItem::Number {
+            value,
+            raw: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn read_number_from_strings_errors() {
+        let res = check(vec![
+            (10, read("A")),
+            (20, data(vec![DataItem::String("x".to_owned())])),
+        ]);
+
+        assert_eq!(
+            res,
+            vec!["Error at line 10: READ A needs a number but every DATA item is a string"]
+        );
+    }
+
+    #[test]
+    fn read_number_from_mixed_data_ok() {
+        let res = check(vec![
+            (10, read("A")),
+            (
+                20,
+                data(vec![DataItem::String("x".to_owned()), number_item(-5)]),
+            ),
+        ]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn read_string_from_numbers_ok() {
+        let res = check(vec![(10, read("A$")), (20, data(vec![number_item(-5)]))]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn read_real_into_number_ok() {
+        let res = check(vec![
+            (10, read("A")),
+            (
+                20,
+                data(vec![DataItem::Real {
+                    value: 3.25,
+                    raw: "3.25".to_owned(),
+                }]),
+            ),
+        ]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn read_real_into_string_ok() {
+        let res = check(vec![
+            (10, read("A$")),
+            (
+                20,
+                data(vec![DataItem::Real {
+                    value: 3.25,
+                    raw: "3.25".to_owned(),
+                }]),
+            ),
+        ]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn real_assignment_keeps_fraction() {
+        let res = check(vec![(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: Expression::Binary {
+                    left: Box::new(Expression::Number(1)),
+                    op: BinaryOperator::Add,
+                    right: Box::new(Expression::Float(0.5)),
+                },
+            },
+        )]);
+
+        assert!(res.is_empty());
+    }
+
+    #[test]
+    fn compare_real_with_integer_ok() {
+        let res = check(vec![(
+            10,
+            Statement::If {
+                condition: Expression::Binary {
+                    left: Box::new(Expression::Float(0.5)),
+                    op: BinaryOperator::Lt,
+                    right: Box::new(Expression::Number(1)),
+                },
+                then: Box::new(Statement::End),
+                else_: None,
+            },
+        )]);
+
+        assert!(res.is_empty());
     }
 }
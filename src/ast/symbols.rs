@@ -0,0 +1,359 @@
+use super::{
+    node::{DataItem, LValue, OutputDevice, Separator, UnaryOperator},
+    semantics::Ty,
+    BinaryOperator, Expression, ExpressionVisitor, Program, ProgramVisitor, Statement,
+    StatementVisitor,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Scalar,
+    Array,
+}
+
+impl std::fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SymbolKind::Scalar => write!(f, "scalar"),
+            SymbolKind::Array => write!(f, "array"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub id: usize,
+    pub name: String,
+    pub ty: Ty,
+    pub kind: SymbolKind,
+    pub first_use: u32,
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>3} {:<4} {} {:<6} line {}",
+            self.id, self.name, self.ty, self.kind, self.first_use
+        )
+    }
+}
+
+/// Every variable in a program, numbered in order of first appearance.
+/// Scalars and arrays with the same name are distinct variables.
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    current_line: u32,
+}
+
+impl SymbolTable {
+    pub fn new(program: &Program) -> Self {
+        let mut table = SymbolTable {
+            symbols: Vec::new(),
+            current_line: 0,
+        };
+
+        program.accept(&mut table);
+        table
+    }
+
+    pub fn lookup(&self, name: &str, kind: SymbolKind) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.name == name && symbol.kind == kind)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    fn declare(&mut self, name: &str, kind: SymbolKind) {
+        if self.lookup(name, kind).is_none() {
+            self.symbols.push(Symbol {
+                id: self.symbols.len(),
+                name: name.to_owned(),
+                ty: Ty::of_variable(name),
+                kind,
+                first_use: self.current_line,
+            });
+        }
+    }
+
+    fn declare_lvalue(&mut self, lvalue: &LValue) {
+        match lvalue {
+            LValue::Variable(name) => self.declare(name, SymbolKind::Scalar),
+            LValue::ArrayElement { variable, indices } => {
+                self.declare(variable, SymbolKind::Array);
+                for index in indices {
+                    index.accept(self);
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for SymbolTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for symbol in self.iter() {
+            writeln!(f, "{}", symbol)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> ExpressionVisitor<'a> for SymbolTable {
+    fn visit_number_literal(&mut self, _: i32) {}
+
+    fn visit_float_literal(&mut self, _: f64) {}
+
+    fn visit_string_literal(&mut self, _: &'a str) {}
+
+    fn visit_variable(&mut self, lvalue: &'a LValue) {
+        self.declare_lvalue(lvalue);
+    }
+
+    fn visit_unary_op(&mut self, _: UnaryOperator, operand: &'a Expression) {
+        operand.accept(self);
+    }
+
+    fn visit_binary_op(&mut self, left: &'a Expression, _: BinaryOperator, right: &'a Expression) {
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_varptr(&mut self, operand: &'a Expression) {
+        operand.accept(self);
+    }
+
+    fn visit_function(&mut self, _: &'a str, args: &'a [Expression]) {
+        for arg in args {
+            arg.accept(self);
+        }
+    }
+}
+
+impl<'a> StatementVisitor<'a> for SymbolTable {
+    fn visit_let(&mut self, variable: &'a LValue, expression: &'a Expression) {
+        self.declare_lvalue(variable);
+        expression.accept(self);
+    }
+
+    fn visit_print(
+        &mut self,
+        content: &'a [(Expression, Separator)],
+        _: bool,
+        _: bool,
+        _: OutputDevice,
+    ) {
+        for (item, _) in content {
+            item.accept(self);
+        }
+    }
+
+    fn visit_pause(&mut self, content: &'a [Expression]) {
+        for item in content {
+            item.accept(self);
+        }
+    }
+
+    fn visit_input(
+        &mut self,
+        device: Option<&'a Expression>,
+        prompt: Option<&'a Expression>,
+        variable: &'a LValue,
+    ) {
+        if let Some(device) = device {
+            device.accept(self);
+        }
+        if let Some(prompt) = prompt {
+            prompt.accept(self);
+        }
+        self.declare_lvalue(variable);
+    }
+
+    fn visit_wait(&mut self, time: Option<&'a Expression>) {
+        if let Some(time) = time {
+            time.accept(self);
+        }
+    }
+
+    fn visit_read(&mut self, variables: &'a [LValue]) {
+        for variable in variables {
+            self.declare_lvalue(variable);
+        }
+    }
+
+    fn visit_data(&mut self, _: &'a [DataItem]) {}
+
+    fn visit_restore(&mut self, _: Option<u32>) {}
+
+    fn visit_poke(&mut self, _: u32, _: &'a [u8]) {}
+
+    fn visit_call(&mut self, _: u32) {}
+
+    fn visit_goto(&mut self, _: u32) {}
+
+    fn visit_for(
+        &mut self,
+        variable: &'a str,
+        from: &'a Expression,
+        to: &'a Expression,
+        step: Option<&'a Expression>,
+    ) {
+        self.declare(variable, SymbolKind::Scalar);
+        from.accept(self);
+        to.accept(self);
+        if let Some(step) = step {
+            step.accept(self);
+        }
+    }
+
+    fn visit_next(&mut self, variable: Option<&'a str>) {
+        if let Some(variable) = variable {
+            self.declare(variable, SymbolKind::Scalar);
+        }
+    }
+
+    fn visit_end(&mut self) {}
+
+    fn visit_stop(&mut self) {}
+
+    fn visit_gosub(&mut self, _: u32) {}
+
+    fn visit_on_goto(&mut self, selector: &'a Expression, _: &'a [u32]) {
+        selector.accept(self);
+    }
+
+    fn visit_on_gosub(&mut self, selector: &'a Expression, _: &'a [u32]) {
+        selector.accept(self);
+    }
+
+    fn visit_return(&mut self) {}
+
+    fn visit_if(
+        &mut self,
+        condition: &'a Expression,
+        then: &'a Statement,
+        else_: Option<&'a Statement>,
+    ) {
+        condition.accept(self);
+        then.accept(self);
+        if let Some(else_) = else_ {
+            else_.accept(self);
+        }
+    }
+
+    fn visit_seq(&mut self, statements: &'a [Statement]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn visit_rem(&mut self, _: &'a str) {}
+
+    fn visit_dim(&mut self, variable: &'a str, _: &'a [u32], _: Option<u32>) {
+        self.declare(variable, SymbolKind::Array);
+    }
+
+    fn visit_clear(&mut self, size: Option<&'a Expression>) {
+        if let Some(size) = size {
+            size.accept(self);
+        }
+    }
+
+    fn visit_chain(&mut self, name: &'a Expression) {
+        name.accept(self);
+    }
+}
+
+impl<'a> ProgramVisitor<'a> for SymbolTable {
+    fn visit_program(&mut self, program: &'a Program) {
+        for (line_number, statement) in program.iter() {
+            self.current_line = *line_number;
+            statement.accept(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_and_int_variables() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A$".to_owned()),
+                expression: Expression::String("hi".to_owned()),
+            },
+        );
+        program.add_line(
+            20,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: Expression::Number(1),
+            },
+        );
+
+        let table = SymbolTable::new(&program);
+
+        assert_eq!(
+            table.lookup("A$", SymbolKind::Scalar),
+            Some(&Symbol {
+                id: 0,
+                name: "A$".to_owned(),
+                ty: Ty::String,
+                kind: SymbolKind::Scalar,
+                first_use: 10,
+            })
+        );
+        assert_eq!(
+            table.lookup("A", SymbolKind::Scalar),
+            Some(&Symbol {
+                id: 1,
+                name: "A".to_owned(),
+                ty: Ty::Real,
+                kind: SymbolKind::Scalar,
+                first_use: 20,
+            })
+        );
+    }
+
+    #[test]
+    fn array_and_scalar_are_distinct() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Dim {
+                variable: "B".to_owned(),
+                sizes: vec![10],
+                length: None,
+            },
+        );
+        program.add_line(
+            20,
+            Statement::Let {
+                variable: LValue::ArrayElement {
+                    variable: "B".to_owned(),
+                    indices: vec![Expression::LValue(LValue::Variable("B".to_owned()))],
+                },
+                expression: Expression::Number(1),
+            },
+        );
+
+        let table = SymbolTable::new(&program);
+
+        assert_eq!(table.iter().count(), 2);
+        assert_eq!(
+            table.lookup("B", SymbolKind::Array).map(|s| s.first_use),
+            Some(10)
+        );
+        assert_eq!(
+            table.lookup("B", SymbolKind::Scalar).map(|s| s.first_use),
+            Some(20)
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use super::{Expression, Program, Separator, Statement};
+
+/// Merges runs of string literals in PRINT statements into a single literal,
+/// so `PRINT "a"; "b"; X` becomes `PRINT "ab"; X`. Only literals separated by
+/// `;` are merged, it prints them with nothing in between, so the output is
+/// the same.
+pub fn merge_print_literals(program: &mut Program) {
+    for statement in program.values_mut() {
+        merge_in_statement(statement);
+    }
+}
+
+fn merge_in_statement(statement: &mut Statement) {
+    match statement {
+        Statement::Print { content, .. } => merge_literals(content),
+        Statement::If { then, else_, .. } => {
+            merge_in_statement(then);
+            if let Some(else_) = else_ {
+                merge_in_statement(else_);
+            }
+        }
+        Statement::Seq { statements } => {
+            for inner in statements {
+                merge_in_statement(inner);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn merge_literals(content: &mut Vec<(Expression, Separator)>) {
+    let mut merged: Vec<(Expression, Separator)> = Vec::with_capacity(content.len());
+
+    for (item, separator) in content.drain(..) {
+        match (merged.last_mut(), item) {
+            (
+                Some((Expression::String(last), last_separator @ Separator::Semicolon)),
+                Expression::String(next),
+            ) => {
+                last.push_str(&next);
+                *last_separator = separator;
+            }
+            (_, item) => merged.push((item, separator)),
+        }
+    }
+
+    *content = merged;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::node::{LValue, OutputDevice};
+
+    #[test]
+    fn merges_adjacent_literals() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Print {
+                content: vec![
+                    (Expression::String("a".to_owned()), Separator::Semicolon),
+                    (Expression::String("b".to_owned()), Separator::Semicolon),
+                    (
+                        Expression::LValue(LValue::Variable("X".to_owned())),
+                        Separator::None,
+                    ),
+                ],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        );
+
+        merge_print_literals(&mut program);
+
+        match program.lookup_line(10) {
+            Some(Statement::Print { content, .. }) => assert_eq!(
+                content,
+                &vec![
+                    (Expression::String("ab".to_owned()), Separator::Semicolon),
+                    (
+                        Expression::LValue(LValue::Variable("X".to_owned())),
+                        Separator::None
+                    ),
+                ]
+            ),
+            _ => panic!("Expected PRINT"),
+        }
+    }
+
+    #[test]
+    fn keeps_literals_around_comma() {
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Print {
+                content: vec![
+                    (Expression::String("a".to_owned()), Separator::Comma),
+                    (Expression::String("b".to_owned()), Separator::None),
+                ],
+                continue_line: false,
+                suppress_newline: false,
+                device: OutputDevice::Display,
+            },
+        );
+
+        merge_print_literals(&mut program);
+
+        match program.lookup_line(10) {
+            Some(Statement::Print { content, .. }) => assert_eq!(content.len(), 2),
+            _ => panic!("Expected PRINT"),
+        }
+    }
+}
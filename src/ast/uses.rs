@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{
+    node::{DataItem, LValue, OutputDevice, Separator},
+    Expression, Program, ProgramVisitor, Statement, StatementVisitor,
+};
+
+/// Variables a line reads and writes. Arrays are named by their base name, an
+/// element write also reads the variables in its subscript.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LineUses {
+    pub reads: BTreeSet<String>,
+    pub writes: BTreeSet<String>,
+}
+
+/// The read and write sets of every line, computed once so analyses don't
+/// each have to walk all the expressions again.
+pub struct VariableUses {
+    lines: BTreeMap<u32, LineUses>,
+    current: LineUses,
+}
+
+impl VariableUses {
+    pub fn new(program: &Program) -> Self {
+        let mut uses = VariableUses {
+            lines: BTreeMap::new(),
+            current: LineUses::default(),
+        };
+
+        program.accept(&mut uses);
+        uses
+    }
+
+    #[cfg(test)]
+    pub fn line(&self, line_number: u32) -> Option<&LineUses> {
+        self.lines.get(&line_number)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u32, &LineUses)> {
+        self.lines.iter()
+    }
+
+    fn read(&mut self, expression: &Expression) {
+        self.current
+            .reads
+            .extend(expression.variables().map(str::to_owned));
+    }
+
+    fn write(&mut self, lvalue: &LValue) {
+        match lvalue {
+            LValue::Variable(name) => {
+                self.current.writes.insert(name.clone());
+            }
+            LValue::ArrayElement { variable, indices } => {
+                self.current.writes.insert(variable.clone());
+                for index in indices {
+                    self.read(index);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> StatementVisitor<'a> for VariableUses {
+    fn visit_let(&mut self, variable: &'a LValue, expression: &'a Expression) {
+        self.write(variable);
+        self.read(expression);
+    }
+
+    fn visit_print(
+        &mut self,
+        content: &'a [(Expression, Separator)],
+        _: bool,
+        _: bool,
+        _: OutputDevice,
+    ) {
+        for (item, _) in content {
+            self.read(item);
+        }
+    }
+
+    fn visit_pause(&mut self, content: &'a [Expression]) {
+        for item in content {
+            self.read(item);
+        }
+    }
+
+    fn visit_input(
+        &mut self,
+        device: Option<&'a Expression>,
+        prompt: Option<&'a Expression>,
+        variable: &'a LValue,
+    ) {
+        for expression in [device, prompt].into_iter().flatten() {
+            self.read(expression);
+        }
+        self.write(variable);
+    }
+
+    fn visit_wait(&mut self, time: Option<&'a Expression>) {
+        if let Some(time) = time {
+            self.read(time);
+        }
+    }
+
+    fn visit_read(&mut self, variables: &'a [LValue]) {
+        for variable in variables {
+            self.write(variable);
+        }
+    }
+
+    fn visit_data(&mut self, _: &'a [DataItem]) {}
+
+    fn visit_restore(&mut self, _: Option<u32>) {}
+
+    fn visit_poke(&mut self, _: u32, _: &'a [u8]) {}
+
+    fn visit_call(&mut self, _: u32) {}
+
+    fn visit_goto(&mut self, _: u32) {}
+
+    fn visit_for(
+        &mut self,
+        variable: &'a str,
+        from: &'a Expression,
+        to: &'a Expression,
+        step: Option<&'a Expression>,
+    ) {
+        self.current.writes.insert(variable.to_owned());
+        for expression in [Some(from), Some(to), step].into_iter().flatten() {
+            self.read(expression);
+        }
+    }
+
+    // NEXT adds the step to the loop variable
+    fn visit_next(&mut self, variable: Option<&'a str>) {
+        if let Some(variable) = variable {
+            self.current.reads.insert(variable.to_owned());
+            self.current.writes.insert(variable.to_owned());
+        }
+    }
+
+    fn visit_end(&mut self) {}
+
+    fn visit_stop(&mut self) {}
+
+    fn visit_gosub(&mut self, _: u32) {}
+
+    fn visit_on_goto(&mut self, selector: &'a Expression, _: &'a [u32]) {
+        self.read(selector);
+    }
+
+    fn visit_on_gosub(&mut self, selector: &'a Expression, _: &'a [u32]) {
+        self.read(selector);
+    }
+
+    fn visit_return(&mut self) {}
+
+    fn visit_if(
+        &mut self,
+        condition: &'a Expression,
+        then: &'a Statement,
+        else_: Option<&'a Statement>,
+    ) {
+        self.read(condition);
+        then.accept(self);
+        if let Some(else_) = else_ {
+            else_.accept(self);
+        }
+    }
+
+    fn visit_seq(&mut self, statements: &'a [Statement]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn visit_rem(&mut self, _: &'a str) {}
+
+    fn visit_dim(&mut self, _: &'a str, _: &'a [u32], _: Option<u32>) {}
+
+    fn visit_clear(&mut self, size: Option<&'a Expression>) {
+        if let Some(size) = size {
+            self.read(size);
+        }
+    }
+
+    fn visit_chain(&mut self, name: &'a Expression) {
+        self.read(name);
+    }
+}
+
+impl<'a> ProgramVisitor<'a> for VariableUses {
+    fn visit_program(&mut self, program: &'a Program) {
+        for (line_number, statement) in program.iter() {
+            statement.accept(self);
+            self.lines
+                .insert(*line_number, std::mem::take(&mut self.current));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BinaryOperator;
+
+    #[test]
+    fn let_reads_and_writes() {
+        // 10 A = B + C
+        let mut program = Program::new();
+        program.add_line(
+            10,
+            Statement::Let {
+                variable: LValue::Variable("A".to_owned()),
+                expression: Expression::Binary {
+                    left: Box::new(Expression::LValue(LValue::Variable("B".to_owned()))),
+                    op: BinaryOperator::Add,
+                    right: Box::new(Expression::LValue(LValue::Variable("C".to_owned()))),
+                },
+            },
+        );
+
+        let uses = VariableUses::new(&program);
+
+        assert_eq!(
+            uses.line(10),
+            Some(&LineUses {
+                reads: BTreeSet::from(["B".to_owned(), "C".to_owned()]),
+                writes: BTreeSet::from(["A".to_owned()]),
+            })
+        );
+    }
+}
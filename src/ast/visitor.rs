@@ -1,10 +1,11 @@
 use super::{
-    node::{DataItem, LValue, UnaryOperator},
+    node::{DataItem, LValue, OutputDevice, Separator, UnaryOperator},
     BinaryOperator, Expression, Program, Statement,
 };
 
 pub trait ExpressionVisitor<'a, RetTy = ()> {
     fn visit_number_literal(&mut self, num: i32) -> RetTy;
+    fn visit_float_literal(&mut self, num: f64) -> RetTy;
     fn visit_string_literal(&mut self, content: &'a str) -> RetTy;
     fn visit_variable(&mut self, lvalue: &'a LValue) -> RetTy;
     fn visit_unary_op(&mut self, op: UnaryOperator, operand: &'a Expression) -> RetTy;
@@ -14,25 +15,41 @@ pub trait ExpressionVisitor<'a, RetTy = ()> {
         op: BinaryOperator,
         right: &'a Expression,
     ) -> RetTy;
+    fn visit_varptr(&mut self, operand: &'a Expression) -> RetTy;
+    fn visit_function(&mut self, name: &'a str, args: &'a [Expression]) -> RetTy;
 }
 
 impl<'a> Expression {
     pub fn accept<V: ExpressionVisitor<'a, RetTy>, RetTy>(&'a self, visitor: &mut V) -> RetTy {
         match self {
             Expression::Number(num) => visitor.visit_number_literal(*num),
+            Expression::Float(num) => visitor.visit_float_literal(*num),
             Expression::String(content) => visitor.visit_string_literal(content),
             Expression::LValue(variable) => visitor.visit_variable(variable),
             Expression::Unary { op, operand } => visitor.visit_unary_op(*op, operand),
             Expression::Binary { left, op, right } => visitor.visit_binary_op(left, *op, right),
+            Expression::Varptr(operand) => visitor.visit_varptr(operand),
+            Expression::Call { name, args } => visitor.visit_function(name, args),
         }
     }
 }
 
 pub trait StatementVisitor<'a, RetTy = ()> {
     fn visit_let(&mut self, variable: &'a LValue, expression: &'a Expression) -> RetTy;
-    fn visit_print(&mut self, content: &'a [Expression]) -> RetTy;
+    fn visit_print(
+        &mut self,
+        content: &'a [(Expression, Separator)],
+        continue_line: bool,
+        suppress_newline: bool,
+        device: OutputDevice,
+    ) -> RetTy;
     fn visit_pause(&mut self, content: &'a [Expression]) -> RetTy;
-    fn visit_input(&mut self, prompt: Option<&'a Expression>, variable: &'a LValue) -> RetTy;
+    fn visit_input(
+        &mut self,
+        device: Option<&'a Expression>,
+        prompt: Option<&'a Expression>,
+        variable: &'a LValue,
+    ) -> RetTy;
     fn visit_wait(&mut self, time: Option<&'a Expression>) -> RetTy;
     fn visit_read(&mut self, variables: &'a [LValue]) -> RetTy;
     fn visit_data(&mut self, values: &'a [DataItem]) -> RetTy;
@@ -47,9 +64,12 @@ pub trait StatementVisitor<'a, RetTy = ()> {
         to: &'a Expression,
         step: Option<&'a Expression>,
     ) -> RetTy;
-    fn visit_next(&mut self, variable: &'a str) -> RetTy;
+    fn visit_next(&mut self, variable: Option<&'a str>) -> RetTy;
     fn visit_end(&mut self) -> RetTy;
+    fn visit_stop(&mut self) -> RetTy;
     fn visit_gosub(&mut self, line_number: u32) -> RetTy;
+    fn visit_on_goto(&mut self, selector: &'a Expression, targets: &'a [u32]) -> RetTy;
+    fn visit_on_gosub(&mut self, selector: &'a Expression, targets: &'a [u32]) -> RetTy;
     fn visit_return(&mut self) -> RetTy;
     fn visit_if(
         &mut self,
@@ -59,7 +79,9 @@ pub trait StatementVisitor<'a, RetTy = ()> {
     ) -> RetTy;
     fn visit_seq(&mut self, statements: &'a [Statement]) -> RetTy;
     fn visit_rem(&mut self, content: &'a str) -> RetTy;
-    fn visit_dim(&mut self, variable: &'a str, size: u32, length: Option<u32>) -> RetTy;
+    fn visit_dim(&mut self, variable: &'a str, sizes: &'a [u32], length: Option<u32>) -> RetTy;
+    fn visit_clear(&mut self, size: Option<&'a Expression>) -> RetTy;
+    fn visit_chain(&mut self, name: &'a Expression) -> RetTy;
 }
 
 impl<'a> Statement {
@@ -67,16 +89,30 @@ impl<'a> Statement {
         match self {
             Statement::Dim {
                 variable,
-                size,
+                sizes,
                 length,
-            } => visitor.visit_dim(variable, *size, *length),
+            } => visitor.visit_dim(variable, sizes, *length),
             Statement::Let {
                 variable,
                 expression,
             } => visitor.visit_let(variable, expression),
-            Statement::Print { content } => visitor.visit_print(content.as_slice()),
+            Statement::Print {
+                content,
+                continue_line,
+                suppress_newline,
+                device,
+            } => visitor.visit_print(
+                content.as_slice(),
+                *continue_line,
+                *suppress_newline,
+                *device,
+            ),
             Statement::Pause { content } => visitor.visit_pause(content.as_slice()),
-            Statement::Input { prompt, variable } => visitor.visit_input(prompt.as_ref(), variable),
+            Statement::Input {
+                device,
+                prompt,
+                variable,
+            } => visitor.visit_input(device.as_ref(), prompt.as_ref(), variable),
             Statement::Wait { time } => visitor.visit_wait(time.as_ref()),
             Statement::Data { values } => visitor.visit_data(values.as_slice()),
             Statement::Read { variables } => visitor.visit_read(variables.as_slice()),
@@ -90,9 +126,12 @@ impl<'a> Statement {
                 to,
                 step,
             } => visitor.visit_for(variable, from, to, step.as_ref()),
-            Statement::Next { variable } => visitor.visit_next(variable),
+            Statement::Next { variable } => visitor.visit_next(variable.as_deref()),
             Statement::End => visitor.visit_end(),
+            Statement::Stop => visitor.visit_stop(),
             Statement::GoSub { line_number } => visitor.visit_gosub(*line_number),
+            Statement::OnGoto { selector, targets } => visitor.visit_on_goto(selector, targets),
+            Statement::OnGosub { selector, targets } => visitor.visit_on_gosub(selector, targets),
             Statement::Return => visitor.visit_return(),
             Statement::If {
                 condition,
@@ -101,6 +140,8 @@ impl<'a> Statement {
             } => visitor.visit_if(condition, then, else_.as_deref()),
             Statement::Seq { statements } => visitor.visit_seq(statements),
             Statement::Rem { content } => visitor.visit_rem(content),
+            Statement::Clear { size } => visitor.visit_clear(size.as_ref()),
+            Statement::Chain { name } => visitor.visit_chain(name),
         }
     }
 }
@@ -0,0 +1,835 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::ast::{
+    format_float, BinaryOperator, DataItem, Expression, ExpressionVisitor, LValue, OutputDevice,
+    Program, ProgramVisitor, Separator, Statement, StatementVisitor, SymbolKind, SymbolTable,
+    UnaryOperator,
+};
+
+// String variables hold this many characters unless a DIM says otherwise
+const STRING_LENGTH: u32 = 16;
+
+/// The functions generated code calls for input, output, strings and GOSUB,
+/// written at the top of every generated program.
+pub const RUNTIME_H: &str = include_str!("runtime.h");
+
+#[derive(Debug)]
+pub struct Error {
+    pub line: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Error at line {}: {}", self.line, self.message)
+    }
+}
+
+/// Translates a checked program to C. Every line becomes a label so GOTO maps
+/// onto goto, and GOSUB pushes a return point that RETURN dispatches on.
+/// Numbers are doubles, like the floating point of the PC-1500.
+pub struct CGenerator<'a> {
+    program: &'a Program,
+    symbols: SymbolTable,
+    dims: HashMap<&'a str, (&'a [u32], Option<u32>)>,
+    // String literals, each written once and referred to by index
+    strings: Vec<&'a str>,
+    body: Vec<String>,
+    depth: usize,
+    // Open FOR loops, innermost last
+    loops: Vec<(&'a str, usize)>,
+    loop_count: usize,
+    return_points: usize,
+    // Jump targets past the last line, each traps at run time
+    missing_lines: BTreeSet<u32>,
+    line: u32,
+    errors: Vec<Error>,
+}
+
+impl<'a> CGenerator<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        CGenerator {
+            program,
+            symbols: SymbolTable::new(program),
+            dims: HashMap::new(),
+            strings: Vec::new(),
+            body: Vec::new(),
+            depth: 1,
+            loops: Vec::new(),
+            loop_count: 0,
+            return_points: 0,
+            missing_lines: BTreeSet::new(),
+            line: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn generate(mut self) -> Result<String, Vec<Error>> {
+        self.program.accept(&mut self);
+        let declarations = self.declarations();
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        let mut output = self.emit_runtime();
+        output.extend(declarations);
+        output.push(String::new());
+        output.push("int main(void)".to_owned());
+        output.push("{".to_owned());
+        output.append(&mut self.body);
+        output.push("end:".to_owned());
+        output.push("    return 0;".to_owned());
+
+        for line_number in &self.missing_lines {
+            output.push(format!("u{}:", line_number));
+            output.push(format!(
+                "    runtime_error(\"undefined line {}\");",
+                line_number
+            ));
+        }
+
+        if self.return_points > 0 {
+            output.push("return_dispatch:".to_owned());
+            output.push("    switch (pop_return()) {".to_owned());
+            for point in 0..self.return_points {
+                output.push(format!("    case {}: goto r{};", point, point));
+            }
+            output.push("    }".to_owned());
+            output.push("    return 0;".to_owned());
+        }
+
+        output.push("}".to_owned());
+        output.push(String::new());
+        Ok(output.join("\n"))
+    }
+
+    /// The runtime followed by the string table, so the program compiles on
+    /// its own.
+    pub fn emit_runtime(&self) -> Vec<String> {
+        let mut output: Vec<String> = RUNTIME_H.lines().map(str::to_owned).collect();
+        output.push(String::new());
+
+        // C does not allow an empty initializer list
+        if !self.strings.is_empty() {
+            output.push("static const char *strings[] = {".to_owned());
+            for string in &self.strings {
+                output.push(format!("    {},", c_string(string)));
+            }
+            output.push("};".to_owned());
+            output.push(String::new());
+        }
+
+        output
+    }
+
+    fn declarations(&mut self) -> Vec<String> {
+        let mut declarations = Vec::new();
+
+        for symbol in self.symbols.iter() {
+            let (sizes, length) = match symbol.kind {
+                SymbolKind::Scalar => (&[][..], None),
+                SymbolKind::Array => match self.dims.get(symbol.name.as_str()) {
+                    Some(&dim) => dim,
+                    None => {
+                        self.errors.push(Error {
+                            line: symbol.first_use,
+                            message: format!("Array {} is used without a DIM", symbol.name),
+                        });
+                        continue;
+                    }
+                },
+            };
+
+            let mut extents: String = sizes.iter().map(|&size| extent(size)).collect();
+            // Strings are arrays of characters with room for the terminator
+            let ty = if symbol.name.ends_with('$') {
+                extents.push_str(&extent(length.unwrap_or(STRING_LENGTH)));
+                "char"
+            } else {
+                "double"
+            };
+
+            declarations.push(format!("static {} v{}{};", ty, symbol.id, extents));
+        }
+
+        for index in 0..self.loop_count {
+            declarations.push(format!("static double f{}_to, f{}_step;", index, index));
+        }
+
+        declarations
+    }
+
+    fn emit(&mut self, code: String) {
+        self.body
+            .push(format!("{}{}", "    ".repeat(self.depth), code));
+    }
+
+    fn emit_label(&mut self, label: String) {
+        self.body.push(format!("{}:", label));
+    }
+
+    // Before C23 a label has to be followed by a statement
+    fn end_block(&mut self) {
+        if self.body.last().is_some_and(|line| line.ends_with(':')) {
+            self.emit(";".to_owned());
+        }
+        self.depth -= 1;
+    }
+
+    fn unsupported(&mut self, what: &str) {
+        self.errors.push(Error {
+            line: self.line,
+            message: format!("{} is not supported in C output", what),
+        });
+    }
+
+    // A jump to a missing line continues at the next one, or stops the
+    // program with an error when there is none
+    fn label(&mut self, line_number: u32) -> String {
+        match self.program.lookup_line_or_next(line_number) {
+            Some((target, _)) => format!("l{}", target),
+            None => {
+                self.missing_lines.insert(line_number);
+                format!("u{}", line_number)
+            }
+        }
+    }
+
+    fn variable(&self, name: &str, kind: SymbolKind) -> String {
+        let symbol = self
+            .symbols
+            .lookup(name, kind)
+            .expect("every variable is in the symbol table");
+        format!("v{}", symbol.id)
+    }
+
+    fn lvalue(&mut self, lvalue: &'a LValue) -> String {
+        match lvalue {
+            LValue::Variable(name) => self.variable(name, SymbolKind::Scalar),
+            LValue::ArrayElement { variable, indices } => {
+                let mut code = self.variable(variable, SymbolKind::Array);
+                for index in indices {
+                    let index = index.accept(self);
+                    code.extend(["[(long)(", &index, ")]"]);
+                }
+                code
+            }
+        }
+    }
+
+    fn assign(&mut self, target: String, is_string: bool, value: String) {
+        if is_string {
+            self.emit(format!(
+                "str_set({}, sizeof {}, {});",
+                target, target, value
+            ));
+        } else {
+            self.emit(format!("{} = {};", target, value));
+        }
+    }
+
+    fn print(&mut self, item: &'a Expression) {
+        let value = item.accept(self);
+        if is_string(item) {
+            self.emit(format!("print_str({});", value));
+        } else {
+            self.emit(format!("print_num({});", value));
+        }
+    }
+
+    fn return_point(&mut self) -> usize {
+        let point = self.return_points;
+        self.return_points += 1;
+        point
+    }
+}
+
+fn extent(size: u32) -> String {
+    format!("[{}]", u64::from(size) + 1)
+}
+
+fn is_string_lvalue(lvalue: &LValue) -> bool {
+    match lvalue {
+        LValue::Variable(name) | LValue::ArrayElement { variable: name, .. } => name.ends_with('$'),
+    }
+}
+
+fn is_string(expression: &Expression) -> bool {
+    match expression {
+        Expression::String(_) => true,
+        Expression::LValue(lvalue) => is_string_lvalue(lvalue),
+        Expression::Call { name, .. } => name.ends_with('$'),
+        Expression::Binary {
+            left,
+            op: BinaryOperator::Add | BinaryOperator::Concat,
+            ..
+        } => is_string(left),
+        _ => false,
+    }
+}
+
+// Characters outside printable ASCII are written as octal escapes of their
+// UTF-8 bytes
+fn c_string(content: &str) -> String {
+    let mut literal = String::from("\"");
+
+    for c in content.chars() {
+        match c {
+            '"' | '\\' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            ' '..='~' => literal.push(c),
+            _ => {
+                let mut bytes = [0; 4];
+                for byte in c.encode_utf8(&mut bytes).bytes() {
+                    literal.push('\\');
+                    for shift in [6, 3, 0] {
+                        literal.push(char::from(b'0' + ((byte >> shift) & 7)));
+                    }
+                }
+            }
+        }
+    }
+
+    literal.push('"');
+    literal
+}
+
+impl<'a> ExpressionVisitor<'a, String> for CGenerator<'a> {
+    fn visit_number_literal(&mut self, num: i32) -> String {
+        format!("{}.0", num)
+    }
+
+    fn visit_float_literal(&mut self, num: f64) -> String {
+        format_float(num)
+    }
+
+    fn visit_string_literal(&mut self, content: &'a str) -> String {
+        let index = match self.strings.iter().position(|&string| string == content) {
+            Some(index) => index,
+            None => {
+                self.strings.push(content);
+                self.strings.len() - 1
+            }
+        };
+
+        format!("strings[{}]", index)
+    }
+
+    fn visit_variable(&mut self, lvalue: &'a LValue) -> String {
+        self.lvalue(lvalue)
+    }
+
+    fn visit_unary_op(&mut self, op: UnaryOperator, operand: &'a Expression) -> String {
+        let operand = operand.accept(self);
+        match op {
+            UnaryOperator::Plus => format!("(+{})", operand),
+            UnaryOperator::Minus => format!("(-{})", operand),
+            UnaryOperator::Not => format!("((double)~(long)({}))", operand),
+        }
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        left: &'a Expression,
+        op: BinaryOperator,
+        right: &'a Expression,
+    ) -> String {
+        let strings = is_string(left);
+        let left = left.accept(self);
+        let right = right.accept(self);
+
+        let comparison = match op {
+            BinaryOperator::Add if strings => return format!("str_concat({}, {})", left, right),
+            BinaryOperator::Concat => return format!("str_concat({}, {})", left, right),
+            BinaryOperator::Add
+            | BinaryOperator::Sub
+            | BinaryOperator::Mul
+            | BinaryOperator::Div => return format!("({} {} {})", left, op, right),
+            BinaryOperator::Pow => return format!("pow({}, {})", left, right),
+            // AND, OR and MOD work on the integer parts
+            BinaryOperator::Mod => return format!("fmod(trunc({}), trunc({}))", left, right),
+            BinaryOperator::And => {
+                return format!("((double)((long)({}) & (long)({})))", left, right)
+            }
+            BinaryOperator::Or => {
+                return format!("((double)((long)({}) | (long)({})))", left, right)
+            }
+            BinaryOperator::Eq => "==",
+            BinaryOperator::Ne => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::Le => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::Ge => ">=",
+        };
+
+        if strings {
+            format!("(str_compare({}, {}) {} 0)", left, right, comparison)
+        } else {
+            format!("({} {} {})", left, comparison, right)
+        }
+    }
+
+    fn visit_varptr(&mut self, _: &'a Expression) -> String {
+        self.unsupported("VARPTR");
+        "0".to_owned()
+    }
+
+    fn visit_function(&mut self, name: &'a str, args: &'a [Expression]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.accept(self)).collect();
+        let function = match name {
+            "ASC" => "str_asc",
+            "CHR$" => "str_chr",
+            "LEFT$" => "str_left",
+            "LEN" => "str_len",
+            "MID$" => "str_mid",
+            "RIGHT$" => "str_right",
+            "STR$" => "str_str",
+            "VAL" => "str_val",
+            _ => {
+                self.unsupported(name);
+                return "0".to_owned();
+            }
+        };
+
+        format!("{}({})", function, args.join(", "))
+    }
+}
+
+impl<'a> StatementVisitor<'a> for CGenerator<'a> {
+    fn visit_let(&mut self, variable: &'a LValue, expression: &'a Expression) {
+        let value = expression.accept(self);
+        let target = self.lvalue(variable);
+        self.assign(target, is_string_lvalue(variable), value);
+    }
+
+    // LPRINT goes to standard output as well, there is no printer to send it to
+    fn visit_print(
+        &mut self,
+        content: &'a [(Expression, Separator)],
+        _: bool,
+        suppress_newline: bool,
+        device: OutputDevice,
+    ) {
+        for (item, separator) in content {
+            self.print(item);
+            if *separator == Separator::Comma {
+                self.emit("print_tab();".to_owned());
+            }
+        }
+        if !suppress_newline {
+            self.emit("print_newline();".to_owned());
+        }
+        if device == OutputDevice::Display {
+            self.emit("print_wait();".to_owned());
+        }
+    }
+
+    fn visit_pause(&mut self, content: &'a [Expression]) {
+        for item in content {
+            self.print(item);
+        }
+        self.emit("print_newline();".to_owned());
+        self.emit("pause_display();".to_owned());
+    }
+
+    fn visit_input(
+        &mut self,
+        device: Option<&'a Expression>,
+        prompt: Option<&'a Expression>,
+        variable: &'a LValue,
+    ) {
+        if device.is_some() {
+            self.unsupported("INPUT #");
+            return;
+        }
+
+        if let Some(prompt) = prompt.filter(|prompt| is_string(prompt)) {
+            let prompt = prompt.accept(self);
+            self.emit(format!("print_str({});", prompt));
+        }
+
+        let target = self.lvalue(variable);
+        if is_string_lvalue(variable) {
+            self.assign(target, true, "input_str()".to_owned());
+        } else {
+            self.assign(target, false, "input_num()".to_owned());
+        }
+    }
+
+    // Only changes how long PRINT waits on the display
+    fn visit_wait(&mut self, time: Option<&'a Expression>) {
+        match time {
+            Some(time) => {
+                let time = time.accept(self);
+                self.emit(format!("set_wait({});", time));
+            }
+            None => self.emit("reset_wait();".to_owned()),
+        }
+    }
+
+    // Only read through READ
+    fn visit_data(&mut self, _: &'a [DataItem]) {}
+
+    fn visit_read(&mut self, _: &'a [LValue]) {
+        self.unsupported("READ");
+    }
+
+    fn visit_restore(&mut self, _: Option<u32>) {
+        self.unsupported("RESTORE");
+    }
+
+    fn visit_poke(&mut self, _: u32, _: &'a [u8]) {
+        self.unsupported("POKE");
+    }
+
+    fn visit_call(&mut self, _: u32) {
+        self.unsupported("CALL");
+    }
+
+    fn visit_goto(&mut self, line_number: u32) {
+        let label = self.label(line_number);
+        self.emit(format!("goto {};", label));
+    }
+
+    // Loops are matched to their NEXT in source order. A loop stays open after
+    // its NEXT so another NEXT further on can close it too.
+    fn visit_for(
+        &mut self,
+        variable: &'a str,
+        from: &'a Expression,
+        to: &'a Expression,
+        step: Option<&'a Expression>,
+    ) {
+        if let Some(open) = self.loops.iter().position(|&(name, _)| name == variable) {
+            self.loops.truncate(open);
+        }
+
+        let index = self.loop_count;
+        self.loop_count += 1;
+        self.loops.push((variable, index));
+
+        let target = self.variable(variable, SymbolKind::Scalar);
+        let from = from.accept(self);
+        self.assign(target, false, from);
+        let to = to.accept(self);
+        self.emit(format!("f{}_to = {};", index, to));
+        let step = step.map_or_else(|| "1.0".to_owned(), |step| step.accept(self));
+        self.emit(format!("f{}_step = {};", index, step));
+        self.emit_label(format!("f{}", index));
+    }
+
+    fn visit_next(&mut self, variable: Option<&'a str>) {
+        let open = match variable {
+            Some(variable) => self.loops.iter().rposition(|&(name, _)| name == variable),
+            None => self.loops.len().checked_sub(1),
+        };
+
+        let open = match open {
+            Some(open) => open,
+            None => {
+                self.unsupported("NEXT without FOR");
+                return;
+            }
+        };
+
+        self.loops.truncate(open + 1);
+        let (name, index) = self.loops[open];
+        let target = self.variable(name, SymbolKind::Scalar);
+        self.assign(
+            target.clone(),
+            false,
+            format!("{} + f{}_step", target, index),
+        );
+        self.emit(format!(
+            "if (f{}_step >= 0 ? {} <= f{}_to : {} >= f{}_to) goto f{};",
+            index, target, index, target, index, index
+        ));
+    }
+
+    fn visit_end(&mut self) {
+        self.emit("goto end;".to_owned());
+    }
+
+    // There is no CONT once the program is compiled
+    fn visit_stop(&mut self) {
+        self.emit("goto end;".to_owned());
+    }
+
+    fn visit_gosub(&mut self, line_number: u32) {
+        let point = self.return_point();
+        let label = self.label(line_number);
+        self.emit(format!("push_return({});", point));
+        self.emit(format!("goto {};", label));
+        self.emit_label(format!("r{}", point));
+    }
+
+    fn visit_on_goto(&mut self, selector: &'a Expression, targets: &'a [u32]) {
+        let selector = selector.accept(self);
+        self.emit(format!("switch ((long)({})) {{", selector));
+        for (position, &target) in targets.iter().enumerate() {
+            let label = self.label(target);
+            self.emit(format!("case {}: goto {};", position + 1, label));
+        }
+        self.emit("}".to_owned());
+    }
+
+    fn visit_on_gosub(&mut self, selector: &'a Expression, targets: &'a [u32]) {
+        let point = self.return_point();
+        let selector = selector.accept(self);
+        self.emit(format!("switch ((long)({})) {{", selector));
+        for (position, &target) in targets.iter().enumerate() {
+            let label = self.label(target);
+            self.emit(format!(
+                "case {}: push_return({}); goto {};",
+                position + 1,
+                point,
+                label
+            ));
+        }
+        self.emit("}".to_owned());
+        self.emit_label(format!("r{}", point));
+    }
+
+    fn visit_return(&mut self) {
+        self.emit("goto return_dispatch;".to_owned());
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &'a Expression,
+        then: &'a Statement,
+        else_: Option<&'a Statement>,
+    ) {
+        let condition = condition.accept(self);
+        self.emit(format!("if ({}) {{", condition));
+        self.depth += 1;
+        then.accept(self);
+        if let Some(else_) = else_ {
+            self.end_block();
+            self.emit("} else {".to_owned());
+            self.depth += 1;
+            else_.accept(self);
+        }
+        self.end_block();
+        self.emit("}".to_owned());
+    }
+
+    fn visit_seq(&mut self, statements: &'a [Statement]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn visit_rem(&mut self, _: &'a str) {}
+
+    // Arrays are declared up front with the sizes of their DIM
+    fn visit_dim(&mut self, variable: &'a str, sizes: &'a [u32], length: Option<u32>) {
+        self.dims.insert(variable, (sizes, length));
+    }
+
+    fn visit_clear(&mut self, _: Option<&'a Expression>) {
+        self.unsupported("CLEAR");
+    }
+
+    fn visit_chain(&mut self, _: &'a Expression) {
+        self.unsupported("CHAIN");
+    }
+}
+
+impl<'a> ProgramVisitor<'a> for CGenerator<'a> {
+    fn visit_program(&mut self, program: &'a Program) {
+        for (&line_number, statement) in program.iter() {
+            self.line = line_number;
+            self.emit_label(format!("l{}", line_number));
+            statement.accept(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::Parser, tokens::Lexer};
+
+    fn generate(input: &str) -> Result<String, Vec<Error>> {
+        let (program, errors) = Parser::new(Lexer::new(input)).parse();
+        assert!(errors.is_empty());
+
+        CGenerator::new(&program).generate()
+    }
+
+    #[test]
+    fn print_sum() {
+        let code = generate("10 PRINT 1+2").unwrap();
+
+        assert!(code.contains("l10:\n    print_num((1.0 + 2.0));\n    print_newline();\n"));
+        assert!(code.contains("int main(void)"));
+
+        let same_line = generate("10 PRINT 1;").unwrap();
+        assert!(same_line.contains("l10:\n    print_num(1.0);\n    print_wait();\nend:"));
+    }
+
+    #[test]
+    fn variables_and_jumps() {
+        let code =
+            generate("10 A = 1\n20 B$ = \"x\"\n30 IF A < 2 THEN GOTO 10\n40 GOTO 45\n50 END")
+                .unwrap();
+
+        assert!(code.contains("static double v0;"));
+        assert!(code.contains("static char v1[17];"));
+        assert!(code.contains("    v0 = 1.0;"));
+        assert!(code.contains("static const char *strings[] = {\n    \"x\",\n};"));
+        assert!(code.contains("    str_set(v1, sizeof v1, strings[0]);"));
+        assert!(code.contains("    if ((v0 < 2.0)) {\n        goto l10;\n    }"));
+        // A jump to a missing line continues at the next one
+        assert!(code.contains("    goto l50;"));
+    }
+
+    #[test]
+    fn gosub_return_dispatch() {
+        let code = generate("10 GOSUB 100\n20 END\n100 RETURN").unwrap();
+
+        assert!(code.contains("    push_return(0);\n    goto l100;\nr0:\n"));
+        assert!(code.contains("    goto return_dispatch;"));
+        assert!(code.contains("    case 0: goto r0;"));
+
+        let nested = generate("10 IF 1 THEN GOSUB 10").unwrap();
+        assert!(nested.contains("        goto l10;\nr0:\n        ;\n    }"));
+    }
+
+    #[test]
+    fn pause_waits_after_printing() {
+        let code = generate("10 PAUSE \"A\"; 1").unwrap();
+
+        assert!(code.contains(
+            "    print_str(strings[0]);\n    print_num(1.0);\n    print_newline();\n    pause_display();\n"
+        ));
+    }
+
+    #[test]
+    fn wait_sets_print_delay() {
+        let code = generate("10 WAIT 60: WAIT\n20 LPRINT 1").unwrap();
+
+        assert!(code.contains("    set_wait(60.0);\n    reset_wait();\n"));
+        // The printer does not wait
+        assert!(code.contains("    print_newline();\nend:"));
+    }
+
+    // NOT is bitwise like on the PC-1500, NOT 0 is -1
+    #[test]
+    fn not_complements_integer_part() {
+        let code = generate("10 IF NOT A THEN END").unwrap();
+
+        assert!(code.contains("    if (((double)~(long)(v0))) {"));
+    }
+
+    #[test]
+    fn jump_past_last_line_traps() {
+        let code = generate("10 GOTO 100\n20 GOSUB 100").unwrap();
+
+        assert!(code.contains("    goto u100;"));
+        assert!(code.contains("u100:\n    runtime_error(\"undefined line 100\");"));
+        assert_eq!(code.matches("u100:").count(), 1);
+    }
+
+    #[test]
+    fn for_next_loop() {
+        let code = generate("10 FOR I = 1 TO 3\n20 NEXT I").unwrap();
+
+        assert!(code.contains("static double f0_to, f0_step;"));
+        assert!(code.contains("    f0_step = 1.0;\nf0:\n"));
+        assert!(code.contains("    if (f0_step >= 0 ? v0 <= f0_to : v0 >= f0_to) goto f0;"));
+    }
+
+    #[test]
+    fn fractional_step_advances() {
+        let code = generate("10 FOR I = 0 TO 1 STEP 0.5\n20 NEXT I").unwrap();
+
+        assert!(code.contains("    v0 = 0.0;\n"));
+        assert!(code.contains("    f0_step = 0.5;\nf0:\n"));
+        assert!(code.contains("    v0 = v0 + f0_step;\n"));
+    }
+
+    #[test]
+    fn unsupported_statements_error() {
+        let errors = generate("10 POKE 30720, 1\n20 INPUT #1, A$").unwrap_err();
+        let messages: Vec<String> = errors.iter().map(Error::to_string).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Error at line 10: POKE is not supported in C output".to_owned(),
+                "Error at line 20: INPUT # is not supported in C output".to_owned(),
+            ]
+        );
+    }
+
+    // Every runtime function the generated code calls is defined in it
+    #[test]
+    fn runtime_defines_used_functions() {
+        let code = generate(
+            "10 INPUT \"N\"; N: INPUT B$\n20 PRINT MID$(B$, 1, N) & LEFT$(B$, 1), LEN(RIGHT$(B$, 2))\n\
+             30 IF B$ < \"x\" THEN PRINT CHR$(ASC(B$)) & STR$(VAL(B$)): GOSUB 50\n40 WAIT N: END\n50 PAUSE B$: WAIT: RETURN",
+        )
+        .unwrap();
+
+        let signatures = [
+            ("print_num(", "void print_num(double value)"),
+            ("print_str(", "void print_str(const char *value)"),
+            ("print_tab(", "void print_tab(void)"),
+            ("print_newline(", "void print_newline(void)"),
+            ("pause_display(", "void pause_display(void)"),
+            ("set_wait(", "void set_wait(double time)"),
+            ("reset_wait(", "void reset_wait(void)"),
+            ("print_wait(", "void print_wait(void)"),
+            ("input_num(", "double input_num(void)"),
+            ("input_str(", "const char *input_str(void)"),
+            (
+                "str_set(",
+                "void str_set(char *dest, size_t size, const char *value)",
+            ),
+            (
+                "str_concat(",
+                "const char *str_concat(const char *left, const char *right)",
+            ),
+            (
+                "str_compare(",
+                "int str_compare(const char *left, const char *right)",
+            ),
+            (
+                "str_left(",
+                "const char *str_left(const char *value, double count)",
+            ),
+            (
+                "str_right(",
+                "const char *str_right(const char *value, double count)",
+            ),
+            (
+                "str_mid(",
+                "const char *str_mid(const char *value, double start, double count)",
+            ),
+            ("str_len(", "double str_len(const char *value)"),
+            ("str_chr(", "const char *str_chr(double code)"),
+            ("str_asc(", "double str_asc(const char *value)"),
+            ("str_str(", "const char *str_str(double value)"),
+            ("str_val(", "double str_val(const char *value)"),
+            ("push_return(", "void push_return(int point)"),
+            ("pop_return(", "int pop_return(void)"),
+        ];
+
+        let main = code.split("int main(void)").nth(1).unwrap();
+        for (call, signature) in signatures {
+            assert!(main.contains(call), "{} is not used", call);
+            assert!(RUNTIME_H.contains(signature), "{} is not defined", call);
+        }
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(c_string("a\"b\\"), "\"a\\\"b\\\\\"");
+        assert_eq!(c_string("é"), "\"\\303\\251\"");
+    }
+}
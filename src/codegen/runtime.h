@@ -0,0 +1,231 @@
+/* Runtime for programs compiled from PC-1500 BASIC, link with -lm */
+#define _POSIX_C_SOURCE 199309L
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+/* Strings on the PC-1500 are at most 80 characters */
+#define STRING_MAX 80
+#define TEMP_STRINGS 8
+#define GOSUB_DEPTH 64
+/* PRINT A, B shows each item on its own half of the 26 column display */
+#define PRINT_ZONE 13
+/* PAUSE shows its output for about 0.85 seconds */
+#define PAUSE_SECONDS 0.85
+/* WAIT counts in 64ths of a second */
+#define WAIT_TICKS 64.0
+
+static void runtime_error(const char *message)
+{
+    fflush(stdout);
+    fprintf(stderr, "%s\n", message);
+    exit(EXIT_FAILURE);
+}
+
+/* String functions return one of a few rotating buffers, enough for the
+   results of a single expression */
+static char *temp_string(void)
+{
+    static char buffers[TEMP_STRINGS][STRING_MAX + 1];
+    static int next;
+
+    next = (next + 1) % TEMP_STRINGS;
+    return buffers[next];
+}
+
+static size_t clamp_length(double value, size_t length)
+{
+    if (value < 0) {
+        return 0;
+    }
+    return value < (double)length ? (size_t)value : length;
+}
+
+static int print_column;
+
+void print_num(double value)
+{
+    print_column += printf("%.10G", value);
+}
+
+void print_str(const char *value)
+{
+    print_column += printf("%s", value);
+}
+
+void print_tab(void)
+{
+    do {
+        putchar(' ');
+        print_column++;
+    } while (print_column % PRINT_ZONE != 0);
+}
+
+void print_newline(void)
+{
+    putchar('\n');
+    print_column = 0;
+}
+
+static void sleep_for(double seconds)
+{
+    struct timespec delay;
+
+    fflush(stdout);
+    delay.tv_sec = (time_t)seconds;
+    delay.tv_nsec = (long)((seconds - (double)delay.tv_sec) * 1e9);
+    nanosleep(&delay, NULL);
+}
+
+void pause_display(void)
+{
+    sleep_for(PAUSE_SECONDS);
+}
+
+/* Without WAIT the PC-1500 stops after PRINT until ENTER is pressed, here
+   the program just goes on */
+static double print_delay;
+
+void set_wait(double time)
+{
+    print_delay = time > 0 ? time / WAIT_TICKS : 0;
+}
+
+void reset_wait(void)
+{
+    print_delay = 0;
+}
+
+void print_wait(void)
+{
+    if (print_delay > 0) {
+        sleep_for(print_delay);
+    }
+}
+
+/* End of input ends the program, like BREAK on the PC-1500 */
+static const char *read_line(void)
+{
+    char *line = temp_string();
+
+    fflush(stdout);
+    if (fgets(line, STRING_MAX + 1, stdin) == NULL) {
+        exit(EXIT_SUCCESS);
+    }
+    line[strcspn(line, "\r\n")] = '\0';
+    return line;
+}
+
+double input_num(void)
+{
+    return strtod(read_line(), NULL);
+}
+
+const char *input_str(void)
+{
+    return read_line();
+}
+
+/* Stores a string into a variable, dropping what does not fit. The value may
+   point into the variable itself, as in A$ = RIGHT$(A$, 3) */
+void str_set(char *dest, size_t size, const char *value)
+{
+    size_t length = strlen(value);
+
+    if (length >= size) {
+        length = size - 1;
+    }
+    memmove(dest, value, length);
+    dest[length] = '\0';
+}
+
+const char *str_concat(const char *left, const char *right)
+{
+    char *result = temp_string();
+
+    snprintf(result, STRING_MAX + 1, "%s%s", left, right);
+    return result;
+}
+
+int str_compare(const char *left, const char *right)
+{
+    return strcmp(left, right);
+}
+
+const char *str_left(const char *value, double count)
+{
+    char *result = temp_string();
+    size_t length = clamp_length(count, strlen(value));
+
+    memcpy(result, value, length);
+    result[length] = '\0';
+    return result;
+}
+
+const char *str_right(const char *value, double count)
+{
+    size_t length = strlen(value);
+
+    return value + length - clamp_length(count, length);
+}
+
+const char *str_mid(const char *value, double start, double count)
+{
+    size_t length = strlen(value);
+    size_t first = clamp_length(start - 1, length);
+
+    return str_left(value + first, count);
+}
+
+double str_len(const char *value)
+{
+    return (double)strlen(value);
+}
+
+const char *str_chr(double code)
+{
+    char *result = temp_string();
+
+    result[0] = (char)code;
+    result[1] = '\0';
+    return result;
+}
+
+double str_asc(const char *value)
+{
+    return (double)(unsigned char)value[0];
+}
+
+const char *str_str(double value)
+{
+    char *result = temp_string();
+
+    snprintf(result, STRING_MAX + 1, "%.10G", value);
+    return result;
+}
+
+double str_val(const char *value)
+{
+    return strtod(value, NULL);
+}
+
+static int gosub_stack[GOSUB_DEPTH];
+static int gosub_top;
+
+void push_return(int point)
+{
+    if (gosub_top == GOSUB_DEPTH) {
+        runtime_error("GOSUB nested too deeply");
+    }
+    gosub_stack[gosub_top++] = point;
+}
+
+int pop_return(void)
+{
+    if (gosub_top == 0) {
+        runtime_error("RETURN without GOSUB");
+    }
+    return gosub_stack[--gosub_top];
+}
@@ -0,0 +1,20 @@
+/// Which BASIC the source is written in. `Pc1500` accepts only what the
+/// machine's interpreter does, `Generic` also allows common extensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Pc1500,
+    Generic,
+}
+
+impl clap::ValueEnum for Dialect {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Dialect::Pc1500, Dialect::Generic]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Dialect::Pc1500 => Some(clap::builder::PossibleValue::new("pc1500")),
+            Dialect::Generic => Some(clap::builder::PossibleValue::new("generic")),
+        }
+    }
+}
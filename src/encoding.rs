@@ -0,0 +1,96 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Pc1500,
+}
+
+impl clap::ValueEnum for Encoding {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Encoding::Utf8, Encoding::Pc1500]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Encoding::Utf8 => Some(clap::builder::PossibleValue::new("utf8")),
+            Encoding::Pc1500 => Some(clap::builder::PossibleValue::new("pc1500")),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub byte: u8,
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid character 0x{:02X} at byte offset {}",
+            self.byte, self.offset
+        )
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// The device character set is JIS X 0201: ASCII with yen and overline in place
+// of backslash and tilde, plus half-width katakana in the upper half
+fn decode_byte(byte: u8) -> Option<char> {
+    match byte {
+        b'\n' | b'\r' | b'\t' => Some(char::from(byte)),
+        0x5C => Some('\u{A5}'),
+        0x7E => Some('\u{203E}'),
+        0x20..=0x7D => Some(char::from(byte)),
+        0xA1..=0xDF => char::from_u32(0xFF61 + u32::from(byte - 0xA1)),
+        _ => None,
+    }
+}
+
+pub fn decode(input: &[u8], encoding: Encoding) -> Result<String, DecodeError> {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8(input.to_vec()).map_err(|e| {
+            let offset = e.utf8_error().valid_up_to();
+            DecodeError {
+                offset,
+                byte: input[offset],
+            }
+        }),
+        Encoding::Pc1500 => input
+            .iter()
+            .enumerate()
+            .map(|(offset, &byte)| decode_byte(byte).ok_or(DecodeError { offset, byte }))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_ascii() {
+        let res = decode(b"10 PRINT \"HI\"\n", Encoding::Pc1500);
+        assert_eq!(res, Ok("10 PRINT \"HI\"\n".to_owned()));
+    }
+
+    #[test]
+    fn decode_device_characters() {
+        // 0x5C is the yen sign and 0xB1 is half-width katakana A
+        let res = decode(b"\"\x5C\xB1\"", Encoding::Pc1500);
+        assert_eq!(res, Ok("\"\u{A5}\u{FF71}\"".to_owned()));
+    }
+
+    #[test]
+    fn decode_invalid_byte() {
+        let res = decode(b"10 \x80", Encoding::Pc1500);
+        assert_eq!(
+            res,
+            Err(DecodeError {
+                offset: 3,
+                byte: 0x80
+            })
+        );
+    }
+}
@@ -1,34 +1,79 @@
 #[forbid(unsafe_code)]
 mod ast;
+mod codegen;
+mod dialect;
+mod encoding;
+mod preprocess;
 mod tokens;
+mod watch;
 
-use std::fs;
+use std::{fs, path::Path};
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgMatches, Command};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Pass {
     Lex,
+    Expr,
     Parse,
+    Symbols,
     Sem,
     C,
 }
 
 impl clap::ValueEnum for Pass {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Pass::Lex, Pass::Parse, Pass::Sem, Pass::C]
+        &[
+            Pass::Lex,
+            Pass::Expr,
+            Pass::Parse,
+            Pass::Symbols,
+            Pass::Sem,
+            Pass::C,
+        ]
     }
 
     fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
         match self {
             Pass::Lex => Some(clap::builder::PossibleValue::new("lex")),
+            Pass::Expr => Some(clap::builder::PossibleValue::new("expr")),
             Pass::Parse => Some(clap::builder::PossibleValue::new("parse")),
+            Pass::Symbols => Some(clap::builder::PossibleValue::new("symbols")),
             Pass::Sem => Some(clap::builder::PossibleValue::new("sem")),
             Pass::C => Some(clap::builder::PossibleValue::new("c")),
         }
     }
 }
 
+// Parses the input as a single expression and prints it fully parenthesized
+fn parse_expression(input: &str, max_depth: Option<usize>) -> String {
+    let mut parser = ast::ExpressionParser::new(tokens::Lexer::new(input));
+    if let Some(max_depth) = max_depth {
+        parser = parser.max_depth(max_depth);
+    }
+
+    match parser.parse() {
+        Ok(Some(expression)) => ast::Printer::new().build_expression(&expression),
+        Ok(None) => "Expected expression".to_owned(),
+        Err(error) => error.to_string(),
+    }
+}
+
+// START:STEP for --renumber
+fn parse_renumber(value: &str) -> Result<(u32, u32), String> {
+    let (start, step) = value
+        .split_once(':')
+        .ok_or_else(|| "expected START:STEP".to_owned())?;
+    let start = start.parse().map_err(|error| format!("START: {}", error))?;
+    let step = step.parse().map_err(|error| format!("STEP: {}", error))?;
+
+    if step == 0 {
+        return Err("STEP must be at least 1".to_owned());
+    }
+
+    Ok((start, step))
+}
+
 // TODO: use clap for argument parsing
 fn main() {
     let args = Command::new("sbc")
@@ -58,12 +103,128 @@ fn main() {
                 .default_value("parse")
                 .required(false),
         )
+        .arg(
+            Arg::new("encoding")
+                .short('e')
+                .long("encoding")
+                .value_name("ENCODING")
+                .help("Character encoding of the source file")
+                .value_parser(clap::builder::EnumValueParser::<encoding::Encoding>::new())
+                .default_value("utf8")
+                .required(false),
+        )
+        .arg(
+            Arg::new("dialect")
+                .long("dialect")
+                .value_name("DIALECT")
+                .help("BASIC dialect the source is written in")
+                .value_parser(clap::builder::EnumValueParser::<dialect::Dialect>::new())
+                .default_value("pc1500")
+                .required(false),
+        )
+        .arg(
+            Arg::new("max-depth")
+                .long("max-depth")
+                .value_name("DEPTH")
+                .help("Deepest nesting of parentheses and operators in an expression")
+                .value_parser(clap::value_parser!(usize))
+                .required(false),
+        )
+        .arg(
+            Arg::new("allow-forward-refs")
+                .long("allow-forward-refs")
+                .help("Report GOTO/GOSUB to undefined lines as warnings")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("auto-lines")
+                .long("auto-lines")
+                .value_name("STEP")
+                .help("Number the lines of a program written without line numbers")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("10")
+                .required(false),
+        )
+        .arg(
+            Arg::new("memory")
+                .long("memory")
+                .value_name("BYTES")
+                .help("RAM available for variables, for machines with a memory module")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1850")
+                .required(false),
+        )
+        .arg(
+            Arg::new("fold-print")
+                .long("fold-print")
+                .help("Merge adjacent string literals in PRINT statements")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("renumber")
+                .long("renumber")
+                .value_name("START:STEP")
+                .help("Print the program with its lines renumbered")
+                .value_parser(parse_renumber)
+                .required(false),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .help("Run the selected pass again whenever the source file changes")
+                .action(clap::ArgAction::SetTrue),
+        )
         .get_matches();
 
+    compile(&args);
+
+    if args.get_flag("watch") {
+        let path = Path::new(args.get_one::<String>("input").unwrap());
+        watch::watch(path, || {
+            println!();
+            compile(&args);
+        });
+    }
+}
+
+fn compile(args: &ArgMatches) {
     // Read file from first argument
-    let input = fs::read_to_string(args.get_one::<String>("input").unwrap()).unwrap();
+    let bytes = match fs::read(args.get_one::<String>("input").unwrap()) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            println!("Error reading source file: {}", error);
+            return;
+        }
+    };
+    let encoding = *args.get_one::<encoding::Encoding>("encoding").unwrap();
+
+    let mut input = match encoding::decode(&bytes, encoding) {
+        Ok(input) => input,
+        Err(error) => {
+            println!("Error reading source file: {}", error);
+            return;
+        }
+    };
+
+    if let Some(&step) = args.get_one::<u32>("auto-lines") {
+        input = match preprocess::number_lines(&input, step) {
+            Ok(input) => input,
+            Err(error) => {
+                println!("Error numbering lines: {}", error);
+                return;
+            }
+        };
+    }
 
     let pass = *args.get_one::<Pass>("pass").unwrap();
+    let max_depth = args.get_one::<usize>("max-depth").copied();
+
+    if pass == Pass::Expr {
+        println!("{}", parse_expression(&input, max_depth));
+        return;
+    }
 
     let tokens = tokens::Lexer::new(&input);
 
@@ -75,9 +236,21 @@ fn main() {
         return;
     }
 
-    let mut parser = ast::Parser::new(tokens);
+    let dialect = *args.get_one::<dialect::Dialect>("dialect").unwrap();
+    let mut parser = ast::Parser::new(tokens).dialect(dialect);
+    if let Some(max_depth) = max_depth {
+        parser = parser.max_depth(max_depth);
+    }
+
+    let (mut program, parse_errors) = parser.parse();
+
+    if args.get_flag("fold-print") {
+        ast::merge_print_literals(&mut program);
+    }
 
-    let (program, parse_errors) = parser.parse();
+    if parse_errors.is_empty() && program.is_empty() {
+        return;
+    }
 
     if !parse_errors.is_empty() {
         println!("Errors parsing program:");
@@ -85,32 +258,92 @@ fn main() {
             println!("{}", error);
         }
     } else {
+        if let Some(&(start, step)) = args.get_one::<(u32, u32)>("renumber") {
+            match program.renumber(start, step) {
+                Ok(()) => print!("{}", ast::Printer::new().build(&program)),
+                Err(error) => println!("Error renumbering program: {}", error),
+            }
+            return;
+        }
+
         if pass == Pass::Parse {
-            // let printer = ast::Printer::new();
-            // let output = printer.build(&program);
-            println!("{program:?}");
+            let printer = ast::Printer::new();
+            print!("{}", printer.build(&program));
+            return;
+        }
+
+        if pass == Pass::Symbols {
+            print!("{}", ast::SymbolTable::new(&program));
             return;
         }
 
-        let sem_checker = ast::SemanticChecker::new(&program);
-        let sem_errors = sem_checker.check();
+        let sem_checker = ast::SemanticChecker::new(&program)
+            .allow_forward_refs(args.get_flag("allow-forward-refs"))
+            .memory_budget(*args.get_one::<usize>("memory").unwrap());
+        let diagnostics = sem_checker.check();
 
-        match sem_errors {
-            Ok(_) => {
-                if pass == Pass::Sem {
-                    println!("No semantic errors found");
-                    return;
-                }
-            }
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == ast::Severity::Error)
+        {
+            return;
+        }
+
+        if pass == Pass::Sem {
+            println!("No semantic errors found");
+            return;
+        }
+
+        let code = match codegen::CGenerator::new(&program).generate() {
+            Ok(code) => code,
             Err(errors) => {
-                println!("Errors in semantic analysis:");
+                println!("Errors generating C code:");
                 for error in errors {
                     println!("{}", error);
                 }
                 return;
             }
+        };
+
+        match args.get_one::<String>("output") {
+            Some(path) => {
+                if let Err(error) = fs::write(path, code) {
+                    println!("Error writing output file: {}", error);
+                }
+            }
+            None => print!("{}", code),
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expr_pass_shows_precedence() {
+        assert_eq!(parse_expression("1 + 2 * 3", None), "(1 + (2 * 3))");
+        assert_eq!(
+            parse_expression("((1))", Some(2)),
+            "line 1: Expression nested too deeply"
+        );
+    }
 
-        todo!("Generate C code");
+    #[test]
+    fn renumber_argument() {
+        assert_eq!(parse_renumber("100:10"), Ok((100, 10)));
+        assert_eq!(parse_renumber("100"), Err("expected START:STEP".to_owned()));
+        assert_eq!(
+            parse_renumber("100:0"),
+            Err("STEP must be at least 1".to_owned())
+        );
+        assert_eq!(
+            parse_renumber("a:10"),
+            Err("START: invalid digit found in string".to_owned())
+        );
     }
 }
@@ -0,0 +1,105 @@
+use crate::tokens::{Lexer, Token};
+
+/// Numbers every non-blank line of a program written without line numbers,
+/// counting up from `step` in increments of `step`.
+///
+/// Jumps by line number are rejected, since the numbers are generated and the
+/// source has no way to name them.
+pub fn number_lines(input: &str, step: u32) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+    let mut line_number = step;
+
+    for (i, line) in input.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(format!(
+                "Line {} already has a line number, which --auto-lines does not allow",
+                i + 1
+            ));
+        }
+
+        let mut tokens = Lexer::new(trimmed).peekable();
+        while let Some(token) = tokens.next() {
+            let numbered_jump = match token {
+                Token::Goto | Token::Gosub => true,
+                Token::Restore => matches!(tokens.peek(), Some(Token::Number(_))),
+                _ => false,
+            };
+
+            if numbered_jump {
+                return Err(format!(
+                    "Line {} jumps to a line number, which --auto-lines does not allow",
+                    i + 1
+                ));
+            }
+        }
+
+        output.push_str(&line_number.to_string());
+        output.push(' ');
+        output.push_str(trimmed);
+        output.push('\n');
+
+        line_number = line_number
+            .checked_add(step)
+            .ok_or_else(|| "Too many lines to number".to_owned())?;
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Parser;
+
+    #[test]
+    fn numbers_lines() {
+        let input = "A = 1\n\nPRINT A\nEND\n";
+        assert_eq!(
+            number_lines(input, 10),
+            Ok("10 A = 1\n20 PRINT A\n30 END\n".to_owned())
+        );
+    }
+
+    // The numbered output is a program the parser accepts
+    #[test]
+    fn numbered_lines_parse() {
+        let input = "INPUT A\nIF A > 1 THEN PRINT A\nEND\n";
+        let output = number_lines(input, 10).unwrap();
+        let (program, errors) = Parser::new(Lexer::new(&output)).parse();
+
+        assert!(errors.is_empty(), "unexpected errors: {}", errors.len());
+        assert_eq!(
+            program.lines.keys().copied().collect::<Vec<_>>(),
+            [10, 20, 30]
+        );
+    }
+
+    #[test]
+    fn custom_step() {
+        let input = "A = 1\nEND";
+        assert_eq!(number_lines(input, 5), Ok("5 A = 1\n10 END\n".to_owned()));
+    }
+
+    #[test]
+    fn rejects_goto() {
+        let input = "A = 1\nGOTO 10";
+        assert_eq!(
+            number_lines(input, 10),
+            Err("Line 2 jumps to a line number, which --auto-lines does not allow".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_existing_numbers() {
+        let input = "10 A = 1";
+        assert_eq!(
+            number_lines(input, 10),
+            Err("Line 1 already has a line number, which --auto-lines does not allow".to_owned())
+        );
+    }
+}
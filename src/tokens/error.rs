@@ -0,0 +1,38 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    MissingDigits,
+    NonAsciiLetter(char),
+    NumberTooLong,
+    UnexpectedCharacter(char),
+    UnterminatedString,
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::MissingDigits => write!(f, "Expected digits after &H"),
+            ErrorKind::NonAsciiLetter(c) => write!(
+                f,
+                "'{}' is not allowed in a name, only the letters A to Z are",
+                c
+            ),
+            ErrorKind::NumberTooLong => write!(f, "Number has too many digits"),
+            ErrorKind::UnexpectedCharacter(c) => write!(f, "Unexpected character '{}'", c),
+            ErrorKind::UnterminatedString => write!(f, "String is missing its closing \""),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub line: usize,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Error at line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for Error {}
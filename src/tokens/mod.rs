@@ -1,39 +1,62 @@
+mod error;
 mod token;
 
+pub use error::{Error as LexError, ErrorKind as LexErrorKind};
 use std::{
+    cell::Cell,
     iter::{FusedIterator, Peekable},
+    rc::Rc,
     str::Chars,
 };
 pub use token::Token;
 
+// Length of RESTORE, no keyword can match an identifier longer than this
+const MAX_KEYWORD_LEN: usize = 7;
+
 pub struct Lexer<'a> {
     input: Peekable<Chars<'a>>,
-    current_line: usize,
+    // Line of the last token, shared so a parser reading through a Peekable
+    // can still tell where it is
+    current_line: Rc<Cell<usize>>,
+    // Line breaks are only counted once the next token starts, so a Newline
+    // token is on the line it ends
+    pending_lines: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             input: input.chars().peekable(),
-            current_line: 0,
+            current_line: Rc::new(Cell::new(1)),
+            pending_lines: 0,
         }
     }
 
     pub fn current_line(&self) -> usize {
-        self.current_line
+        self.current_line.get()
+    }
+
+    /// Follows the line of the last token even after the lexer is moved.
+    pub fn line_counter(&self) -> Rc<Cell<usize>> {
+        Rc::clone(&self.current_line)
     }
 
     fn next_token(&mut self) -> Option<Token> {
         self.skip_whitespace();
 
+        if self.pending_lines > 0 {
+            self.current_line
+                .set(self.current_line.get() + self.pending_lines);
+            self.pending_lines = 0;
+        }
+
         let token = match self.input.next()? {
-            '"' => self
-                .string()
-                .unwrap_or_else(|_| panic!("Unterminated string at line {}", self.current_line)),
+            '"' => self.string(),
             '+' => Token::Plus,
             '-' => Token::Minus,
             '*' => Token::Star,
             '/' => Token::Slash,
+            '^' => Token::Caret,
             '<' => {
                 if self.input.next_if_eq(&'>').is_some() {
                     Token::Diamond
@@ -50,24 +73,35 @@ impl<'a> Lexer<'a> {
                     Token::GreaterThan
                 }
             }
-            '=' => Token::Equal,
+            // Some listings write the operator the other way round
+            '=' => {
+                if self.input.next_if_eq(&'<').is_some() {
+                    Token::LessOrEqual
+                } else if self.input.next_if_eq(&'>').is_some() {
+                    Token::GreaterOrEqual
+                } else {
+                    Token::Equal
+                }
+            }
             ',' => Token::Comma,
             ';' => Token::Semicolon,
             ':' => Token::Colon,
+            '#' => Token::Hash,
+            '&' => self.ampersand(),
             '(' => Token::LeftParen,
             ')' => Token::RightParen,
-            '\n' | '\r' => {
-                self.skip_newline();
+            c @ ('\n' | '\r') => {
+                self.skip_newline(c);
                 Token::Newline
             }
             c if c.is_ascii_alphabetic() => self.identifier(c),
-            c if c.is_ascii_digit() => self
-                .number(c)
-                .unwrap_or_else(|_| panic!("Invalid number at line {}", self.current_line)),
-            other => panic!(
-                "Unexpected character '{}' at line {}",
-                other, self.current_line
-            ),
+            c if c.is_alphabetic() => self.non_ascii_letter(c),
+            c if c.is_ascii_digit() => self.number(c),
+            '.' if self.input.peek().is_some_and(char::is_ascii_digit) => self.number('.'),
+            other => Token::Error(LexError {
+                kind: LexErrorKind::UnexpectedCharacter(other),
+                line: self.current_line(),
+            }),
         };
 
         Some(token)
@@ -77,10 +111,17 @@ impl<'a> Lexer<'a> {
         while self.input.next_if(|&c| matches!(c, ' ' | '\t')).is_some() {}
     }
 
-    // We already know the first character is a whitespace before entering this function
-    fn skip_newline(&mut self) {
-        while self.input.next_if(|&c| matches!(c, '\n' | '\r')).is_some() {
-            self.current_line += 1;
+    // We already know the first character is a line break before entering
+    // this function. \r\n is a single line break.
+    fn skip_newline(&mut self, first: char) {
+        let mut previous = first;
+        self.pending_lines += 1;
+
+        while let Some(c) = self.input.next_if(|&c| matches!(c, '\n' | '\r')) {
+            if !(previous == '\r' && c == '\n') {
+                self.pending_lines += 1;
+            }
+            previous = c;
         }
     }
 
@@ -92,10 +133,16 @@ impl<'a> Lexer<'a> {
         while let Some(c) = self.input.next_if(|&c| c.is_ascii_alphabetic()) {
             ident.push(c);
 
+            if ident.len() > MAX_KEYWORD_LEN {
+                continue;
+            }
+
             // Greedily match a keyword
             let tok = match ident.as_str() {
                 "AND" => Some(Token::And),
                 "CALL" => Some(Token::Call),
+                "CHAIN" => Some(Token::Chain),
+                "CLEAR" => Some(Token::Clear),
                 "DATA" => Some(Token::Data),
                 "DIM" => Some(Token::Dim),
                 "ELSE" => Some(Token::Else),
@@ -106,8 +153,11 @@ impl<'a> Lexer<'a> {
                 "IF" => Some(Token::If),
                 "INPUT" => Some(Token::Input),
                 "LET" => Some(Token::Let),
+                "LPRINT" => Some(Token::LPrint),
+                "MOD" => Some(Token::Mod),
                 "NEXT" => Some(Token::Next),
                 "NOT" => Some(Token::Not),
+                "ON" => Some(Token::On),
                 "OR" => Some(Token::Or),
                 "PAUSE" => Some(Token::Pause),
                 "POKE" => Some(Token::Poke),
@@ -117,8 +167,10 @@ impl<'a> Lexer<'a> {
                 "RESTORE" => Some(Token::Restore),
                 "RETURN" => Some(Token::Return),
                 "STEP" => Some(Token::Step),
+                "STOP" => Some(Token::Stop),
                 "THEN" => Some(Token::Then),
                 "TO" => Some(Token::To),
+                "VARPTR" => Some(Token::Varptr),
                 "WAIT" => Some(Token::Wait),
                 _ => None,
             };
@@ -129,27 +181,126 @@ impl<'a> Lexer<'a> {
         }
 
         let last = self.input.peek().copied();
+        if let Some(c) = last.filter(|c| c.is_alphabetic()) {
+            self.input.next();
+            return self.non_ascii_letter(c);
+        }
+
         if let Some('$') = last {
             ident.push('$');
             self.input.next();
         }
 
-        Token::Identifier(ident.to_owned())
+        Token::Identifier(ident)
+    }
+
+    // Listings typed on other machines sometimes have accented letters in names,
+    // the rest of the name is skipped so it is reported once
+    fn non_ascii_letter(&mut self, letter: char) -> Token {
+        while self
+            .input
+            .next_if(|&c| c.is_alphanumeric() || c == '$')
+            .is_some()
+        {}
+
+        Token::Error(LexError {
+            kind: LexErrorKind::NonAsciiLetter(letter),
+            line: self.current_line(),
+        })
     }
 
-    // We already know the first character is a digit before entering this function
-    fn number(&mut self, first: char) -> Result<Token, ()> {
+    // We already know the first character is a digit or a decimal point
+    // followed by a digit before entering this function
+    fn number(&mut self, first: char) -> Token {
         let mut chars = String::new();
         chars.push(first);
+        self.digits(&mut chars);
+
+        let mut is_float = first == '.';
+        if !is_float && self.input.next_if_eq(&'.').is_some() {
+            chars.push('.');
+            self.digits(&mut chars);
+            is_float = true;
+        }
+
+        // E only starts an exponent when digits follow, 1E is not a number
+        let mut lookahead = self.input.clone();
+        let has_exponent = match (lookahead.next(), lookahead.next(), lookahead.next()) {
+            (Some('E'), Some(c), _) if c.is_ascii_digit() => true,
+            (Some('E'), Some('+' | '-'), Some(c)) if c.is_ascii_digit() => true,
+            _ => false,
+        };
+
+        if has_exponent {
+            chars.push('E');
+            self.input.next();
+            if let Some(sign) = self.input.next_if(|&c| c == '+' || c == '-') {
+                chars.push(sign);
+            }
+            self.digits(&mut chars);
+            is_float = true;
+        }
+
+        if !is_float {
+            return self.number_token(&chars, 10);
+        }
+
+        // Only digits, a point and an exponent reach here, which always parse
+        match chars.parse() {
+            Ok(value) => Token::Float(value),
+            Err(_) => unreachable!("{} is not a valid float", chars),
+        }
+    }
+
+    fn digits(&mut self, chars: &mut String) {
         while let Some(c) = self.input.next_if(|&c| c.is_ascii_digit()) {
             chars.push(c);
         }
+    }
+
+    // Only digits reach here, so the only way parsing fails is overflow
+    fn number_token(&self, digits: &str, radix: u32) -> Token {
+        match i64::from_str_radix(digits, radix) {
+            Ok(value) => Token::Number(value),
+            Err(_) => Token::Error(LexError {
+                kind: LexErrorKind::NumberTooLong,
+                line: self.current_line(),
+            }),
+        }
+    }
+
+    // We already know the first character is an ampersand before entering this function
+    //
+    // & is a number prefix when followed by H (&HFF) or by B and a binary
+    // digit (&B10). &H without hex digits is an error, write A$ & H$ with a
+    // space to concatenate. Anything else, including &B followed by a letter
+    // as in &BC$, is the string concatenation operator.
+    fn ampersand(&mut self) -> Token {
+        let mut lookahead = self.input.clone();
+        let radix = match (lookahead.next(), lookahead.next()) {
+            (Some('H'), _) => 16,
+            (Some('B'), Some('0' | '1')) => 2,
+            _ => return Token::Ampersand,
+        };
+
+        self.input.next(); // Consume the H or B
+        let mut chars = String::new();
+        while let Some(c) = self.input.next_if(|&c| c.is_digit(radix)) {
+            chars.push(c);
+        }
 
-        Ok(Token::Number(chars.parse().map_err(|_e| ())?))
+        if chars.is_empty() {
+            return Token::Error(LexError {
+                kind: LexErrorKind::MissingDigits,
+                line: self.current_line(),
+            });
+        }
+
+        self.number_token(&chars, radix)
     }
 
     // We already know the first character is a double quote before entering this function
-    fn string(&mut self) -> Result<Token, ()> {
+    fn string(&mut self) -> Token {
         // 20 is just a heuristic
         let mut chars = String::with_capacity(20);
 
@@ -157,17 +308,23 @@ impl<'a> Lexer<'a> {
             chars.push(c);
         }
 
-        self.input.next(); // Consume the closing double quote, or newline
+        // The line break is left for the next token
+        if self.input.next_if_eq(&'"').is_none() {
+            return Token::Error(LexError {
+                kind: LexErrorKind::UnterminatedString,
+                line: self.current_line(),
+            });
+        }
 
-        Ok(Token::String(chars.to_owned()))
+        Token::String(chars)
     }
 
     fn comment(&mut self) -> Token {
-        let s: String = self
-            .input
-            .by_ref()
-            .take_while(|&c| c != '\n' && c != '\r')
-            .collect();
+        // Leave the line break for the next token, the comment ends the line
+        let mut s = String::new();
+        while let Some(c) = self.input.next_if(|&c| c != '\n' && c != '\r') {
+            s.push(c);
+        }
 
         Token::Rem(s.trim().to_owned())
     }
@@ -250,6 +407,264 @@ mod tests {
         assert_eq!(lexer.next(), Some(super::Token::RightParen));
     }
 
+    #[test]
+    fn reversed_less_or_equal() {
+        let input = "A=<B";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("A".to_owned())));
+        assert_eq!(lexer.next(), Some(super::Token::LessOrEqual));
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("B".to_owned())));
+    }
+
+    #[test]
+    fn reversed_greater_or_equal() {
+        let input = "A => 1";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("A".to_owned())));
+        assert_eq!(lexer.next(), Some(super::Token::GreaterOrEqual));
+        assert_eq!(lexer.next(), Some(super::Token::Number(1)));
+    }
+
+    #[test]
+    fn equal_negative_number() {
+        let input = "A=-1";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("A".to_owned())));
+        assert_eq!(lexer.next(), Some(super::Token::Equal));
+        assert_eq!(lexer.next(), Some(super::Token::Minus));
+        assert_eq!(lexer.next(), Some(super::Token::Number(1)));
+    }
+
+    #[test]
+    fn hex_number() {
+        let input = "&HFF";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Number(255)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn binary_number() {
+        let input = "&B10";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Number(2)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn float_number() {
+        let input = "3.25";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Float(3.25)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn float_exponent() {
+        let input = "1.5E3 2E-2 .5";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Float(1500.0)));
+        assert_eq!(lexer.next(), Some(super::Token::Float(0.02)));
+        assert_eq!(lexer.next(), Some(super::Token::Float(0.5)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    // Without digits after it the E is the start of a keyword or name
+    #[test]
+    fn number_before_e_keyword() {
+        let input = "1ELSE";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Number(1)));
+        assert_eq!(lexer.next(), Some(super::Token::Else));
+    }
+
+    #[test]
+    fn caret() {
+        let input = "A^2";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("A".to_owned())));
+        assert_eq!(lexer.next(), Some(super::Token::Caret));
+        assert_eq!(lexer.next(), Some(super::Token::Number(2)));
+    }
+
+    #[test]
+    fn hex_zero() {
+        let input = "&H0";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Number(0)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn binary_number_long() {
+        let input = "POKE &H7A00, &B1010";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Poke));
+        assert_eq!(lexer.next(), Some(super::Token::Number(0x7A00)));
+        assert_eq!(lexer.next(), Some(super::Token::Comma));
+        assert_eq!(lexer.next(), Some(super::Token::Number(10)));
+    }
+
+    #[test]
+    fn hex_without_digits() {
+        let input = "&HZ";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(
+            lexer.next(),
+            Some(super::Token::Error(super::LexError {
+                kind: super::LexErrorKind::MissingDigits,
+                line: 1,
+            }))
+        );
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("Z".to_owned())));
+    }
+
+    #[test]
+    fn ampersand_concatenation() {
+        let input = "A$ & B$";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(
+            lexer.next(),
+            Some(super::Token::Identifier("A$".to_owned()))
+        );
+        assert_eq!(lexer.next(), Some(super::Token::Ampersand));
+        assert_eq!(
+            lexer.next(),
+            Some(super::Token::Identifier("B$".to_owned()))
+        );
+    }
+
+    #[test]
+    fn chain_keyword() {
+        let input = "CHAIN \"PROG2\"";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Chain));
+        assert_eq!(lexer.next(), Some(super::Token::String("PROG2".to_owned())));
+    }
+
+    #[test]
+    fn lprint_keyword() {
+        let input = "LPRINT \"x\"";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::LPrint));
+        assert_eq!(lexer.next(), Some(super::Token::String("x".to_owned())));
+    }
+
+    // Keywords are matched greedily as soon as they are complete, so whatever
+    // follows starts a new token
+    #[test]
+    fn keywords_match_greedily() {
+        use super::Token;
+
+        let keywords = [
+            ("AND", Token::And),
+            ("CALL", Token::Call),
+            ("CHAIN", Token::Chain),
+            ("CLEAR", Token::Clear),
+            ("DATA", Token::Data),
+            ("DIM", Token::Dim),
+            ("ELSE", Token::Else),
+            ("END", Token::End),
+            ("FOR", Token::For),
+            ("GOSUB", Token::Gosub),
+            ("GOTO", Token::Goto),
+            ("IF", Token::If),
+            ("INPUT", Token::Input),
+            ("LET", Token::Let),
+            ("LPRINT", Token::LPrint),
+            ("MOD", Token::Mod),
+            ("NEXT", Token::Next),
+            ("NOT", Token::Not),
+            ("ON", Token::On),
+            ("OR", Token::Or),
+            ("PAUSE", Token::Pause),
+            ("POKE", Token::Poke),
+            ("PRINT", Token::Print),
+            ("READ", Token::Read),
+            ("RESTORE", Token::Restore),
+            ("RETURN", Token::Return),
+            ("STEP", Token::Step),
+            ("STOP", Token::Stop),
+            ("THEN", Token::Then),
+            ("TO", Token::To),
+            ("VARPTR", Token::Varptr),
+            ("WAIT", Token::Wait),
+        ];
+
+        for (keyword, token) in keywords {
+            let alone: Vec<Token> = super::Lexer::new(keyword).collect();
+            assert_eq!(alone, vec![token]);
+
+            let input = format!("{}AB$", keyword);
+            let mut lexer = super::Lexer::new(&input);
+            assert!(lexer.next().is_some_and(|t| t.to_string() == keyword));
+            assert_eq!(lexer.next(), Some(Token::Identifier("AB$".to_owned())));
+            assert_eq!(lexer.next(), None);
+        }
+
+        let long: Vec<Token> = super::Lexer::new("ABCDEFGHIJ$").collect();
+        assert_eq!(long, vec![Token::Identifier("ABCDEFGHIJ$".to_owned())]);
+    }
+
+    #[test]
+    fn non_ascii_identifier() {
+        let input = "Lét = 1";
+        let mut lexer = super::Lexer::new(input);
+        let error = super::LexError {
+            kind: super::LexErrorKind::NonAsciiLetter('é'),
+            line: 1,
+        };
+        assert_eq!(lexer.next(), Some(super::Token::Error(error.clone())));
+        assert_eq!(lexer.next(), Some(super::Token::Equal));
+        assert_eq!(lexer.next(), Some(super::Token::Number(1)));
+        assert_eq!(
+            error.to_string(),
+            "Error at line 1: 'é' is not allowed in a name, only the letters A to Z are"
+        );
+    }
+
+    #[test]
+    fn number_wider_than_i32() {
+        let input = "99999999999";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Number(99_999_999_999)));
+    }
+
+    #[test]
+    fn number_too_long() {
+        let input = "99999999999999999999";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(
+            lexer.next(),
+            Some(super::Token::Error(super::LexError {
+                kind: super::LexErrorKind::NumberTooLong,
+                line: 1,
+            }))
+        );
+    }
+
+    #[test]
+    fn clear_keyword() {
+        let input = "CLEAR 100";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Clear));
+        assert_eq!(lexer.next(), Some(super::Token::Number(100)));
+    }
+
+    #[test]
+    fn input_device() {
+        let input = "INPUT #2, A$";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Input));
+        assert_eq!(lexer.next(), Some(super::Token::Hash));
+        assert_eq!(lexer.next(), Some(super::Token::Number(2)));
+        assert_eq!(lexer.next(), Some(super::Token::Comma));
+        assert_eq!(
+            lexer.next(),
+            Some(super::Token::Identifier("A$".to_owned()))
+        );
+    }
+
     #[test]
     fn string_basic() {
         let input = "\"hello\"";
@@ -257,6 +672,36 @@ mod tests {
         assert_eq!(lexer.next(), Some(super::Token::String("hello".to_owned())));
     }
 
+    #[test]
+    fn unterminated_string() {
+        let input = "\"abc\nEND";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(
+            lexer.next(),
+            Some(super::Token::Error(super::LexError {
+                kind: super::LexErrorKind::UnterminatedString,
+                line: 1,
+            }))
+        );
+        assert_eq!(lexer.next(), Some(super::Token::Newline));
+        assert_eq!(lexer.next(), Some(super::Token::End));
+    }
+
+    #[test]
+    fn unexpected_character() {
+        let input = "A @ B";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("A".to_owned())));
+        assert_eq!(
+            lexer.next(),
+            Some(super::Token::Error(super::LexError {
+                kind: super::LexErrorKind::UnexpectedCharacter('@'),
+                line: 1,
+            }))
+        );
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("B".to_owned())));
+    }
+
     #[test]
     fn parenthesized_string() {
         let input = "(\"hello\")";
@@ -273,6 +718,31 @@ mod tests {
         assert_eq!(lexer.next(), Some(super::Token::Rem("hello".to_owned())));
     }
 
+    #[test]
+    fn comment_keeps_newline() {
+        let input = "10 REM hello\n20 END";
+        let mut lexer = super::Lexer::new(input);
+        assert_eq!(lexer.next(), Some(super::Token::Number(10)));
+        assert_eq!(lexer.next(), Some(super::Token::Rem("hello".to_owned())));
+        assert_eq!(lexer.next(), Some(super::Token::Newline));
+        assert_eq!(lexer.next(), Some(super::Token::Number(20)));
+    }
+
+    #[test]
+    fn line_counting() {
+        let mut lexer = super::Lexer::new("A\r\n\nB\nC");
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("A".to_owned())));
+        assert_eq!(lexer.current_line(), 1);
+        // The line break belongs to the line it ends
+        assert_eq!(lexer.next(), Some(super::Token::Newline));
+        assert_eq!(lexer.current_line(), 1);
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("B".to_owned())));
+        assert_eq!(lexer.current_line(), 3);
+        lexer.next();
+        assert_eq!(lexer.next(), Some(super::Token::Identifier("C".to_owned())));
+        assert_eq!(lexer.current_line(), 4);
+    }
+
     #[test]
     fn skip_empty_lines() {
         let input = "REM hello\n\n\nREM world";
@@ -1,7 +1,12 @@
-#[derive(Debug, PartialEq, Eq, Hash)]
+use super::LexError;
+
+#[derive(Debug, PartialEq)]
 pub enum Token {
     Identifier(String),
-    Number(i32),
+    // Wider than the AST's numbers so the parser can report what doesn't fit
+    Number(i64),
+    // A number with a decimal point or an exponent
+    Float(f64),
     String(String),
 
     // --- Keywords ---
@@ -13,17 +18,22 @@ pub enum Token {
     Else,
     Then,
     End,
+    Stop,
     For,
     To,
     Step,
     Next,
+    On,
     Dim,
+    Clear,
     // kinda operator but treated as keyword
     And,
     Or,
     Not,
+    Mod,
     // IO Intrinsics, might as well be keywords
     Print,
+    LPrint,
     Input,
     Pause,
     Wait,
@@ -34,17 +44,23 @@ pub enum Token {
     // Inline assembly
     Poke,
     Call,
+    Varptr,
+    // Program loading
+    Chain,
 
     // Comments, kind of a keyword
     Rem(String),
 
     // --- Symbols ---
+    Ampersand,
+    Caret,
     Colon,
     Comma,
     Diamond,
     Equal,
     GreaterOrEqual,
     GreaterThan,
+    Hash,
     LeftParen,
     LessOrEqual,
     LessThan,
@@ -55,6 +71,9 @@ pub enum Token {
     Semicolon,
     Slash,
     Star,
+
+    // Something the lexer could not make sense of
+    Error(LexError),
 }
 
 impl std::fmt::Display for Token {
@@ -62,9 +81,11 @@ impl std::fmt::Display for Token {
         match self {
             // Keywords
             Token::And => write!(f, "AND"),
+            Token::Clear => write!(f, "CLEAR"),
             Token::Dim => write!(f, "DIM"),
             Token::Else => write!(f, "ELSE"),
             Token::End => write!(f, "END"),
+            Token::Stop => write!(f, "STOP"),
             Token::For => write!(f, "FOR"),
             Token::Gosub => write!(f, "GOSUB"),
             Token::Goto => write!(f, "GOTO"),
@@ -72,6 +93,8 @@ impl std::fmt::Display for Token {
             Token::Let => write!(f, "LET"),
             Token::Next => write!(f, "NEXT"),
             Token::Not => write!(f, "NOT"),
+            Token::Mod => write!(f, "MOD"),
+            Token::On => write!(f, "ON"),
             Token::Or => write!(f, "OR"),
             Token::Return => write!(f, "RETURN"),
             Token::Step => write!(f, "STEP"),
@@ -82,20 +105,26 @@ impl std::fmt::Display for Token {
             Token::Input => write!(f, "INPUT"),
             Token::Pause => write!(f, "PAUSE"),
             Token::Print => write!(f, "PRINT"),
+            Token::LPrint => write!(f, "LPRINT"),
             Token::Read => write!(f, "READ"),
             Token::Restore => write!(f, "RESTORE"),
             Token::Wait => write!(f, "WAIT"),
             Token::Poke => write!(f, "POKE"),
             Token::Call => write!(f, "CALL"),
+            Token::Varptr => write!(f, "VARPTR"),
+            Token::Chain => write!(f, "CHAIN"),
             // Comments
             Token::Rem(content) => write!(f, "REM({})", content),
             // Operators
+            Token::Ampersand => write!(f, "&"),
+            Token::Caret => write!(f, "^"),
             Token::Colon => write!(f, ":"),
             Token::Comma => write!(f, ","),
             Token::Diamond => write!(f, "<>"),
             Token::Equal => write!(f, "="),
             Token::GreaterOrEqual => write!(f, ">="),
             Token::GreaterThan => write!(f, ">"),
+            Token::Hash => write!(f, "#"),
             Token::LeftParen => write!(f, "("),
             Token::LessOrEqual => write!(f, "<="),
             Token::LessThan => write!(f, "<"),
@@ -106,9 +135,11 @@ impl std::fmt::Display for Token {
             Token::Semicolon => write!(f, ";"),
             Token::Slash => write!(f, "/"),
             Token::Star => write!(f, "*"),
+            Token::Error(error) => write!(f, "{}", error),
             // Other
             Token::Identifier(ident) => write!(f, "{}", ident),
             Token::Number(num) => write!(f, "{}", num),
+            Token::Float(num) => write!(f, "{}", num),
             Token::String(string) => write!(f, "\"{}\"", string),
         }
     }
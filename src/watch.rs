@@ -0,0 +1,94 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Polls a file's modification time rather than using OS notifications, so
+/// watching a single file needs no extra dependency and still sees editors
+/// that save by replacing the file. Editors often write a file in several
+/// steps, so a change is only reported once the file has stopped changing
+/// for the debounce period.
+pub struct Watcher {
+    path: PathBuf,
+    debounce: Duration,
+    modified: Option<SystemTime>,
+    changed_at: Option<Instant>,
+}
+
+impl Watcher {
+    pub fn new(path: &Path, debounce: Duration) -> Self {
+        Watcher {
+            path: path.to_owned(),
+            debounce,
+            modified: Self::modified(path),
+            changed_at: None,
+        }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Returns true once per settled change to the file.
+    pub fn poll(&mut self) -> bool {
+        let modified = Self::modified(&self.path);
+        if modified != self.modified {
+            self.modified = modified;
+            self.changed_at = Some(Instant::now());
+            return false;
+        }
+
+        match self.changed_at {
+            Some(changed_at) if changed_at.elapsed() >= self.debounce => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Calls `on_change` every time the file changes, forever.
+pub fn watch(path: &Path, mut on_change: impl FnMut()) -> ! {
+    let mut watcher = Watcher::new(path, DEBOUNCE);
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        if watcher.poll() {
+            on_change();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn change_triggers_once() {
+        let path =
+            std::env::temp_dir().join(format!("basic-1500-watch-test-{}.bas", std::process::id()));
+        fs::write(&path, "10 END\n").unwrap();
+
+        let mut watcher = Watcher::new(&path, Duration::ZERO);
+        assert!(!watcher.poll());
+
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        // The first poll sees the change, the next one reports it once settled
+        assert!(!watcher.poll());
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+
+        fs::remove_file(&path).unwrap();
+    }
+}